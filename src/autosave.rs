@@ -0,0 +1,130 @@
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_UPDATE_TIME: Duration = Duration::from_secs(4);
+
+pub trait Clock {
+    fn now(&self) -> Instant;
+}
+
+#[derive(Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// Decides when the swap file should be written: once the document has been modified and no
+/// key has been pressed for `update_time`. The swap file is written at most once per pause in
+/// typing.
+pub struct Autosave<C> {
+    clock: C,
+    update_time: Duration,
+    last_input: Instant,
+    pending: bool,
+}
+
+impl<C: Clock> Autosave<C> {
+    pub fn new(clock: C) -> Self {
+        let last_input = clock.now();
+
+        Self {
+            clock,
+            update_time: DEFAULT_UPDATE_TIME,
+            last_input,
+            pending: false,
+        }
+    }
+
+    pub fn set_update_time(&mut self, update_time: Duration) {
+        self.update_time = update_time;
+    }
+
+    /// Records a key press, restarting the idle time.
+    pub fn input(&mut self) {
+        self.last_input = self.clock.now();
+        self.pending = true;
+    }
+
+    /// Whether the swap file should be written on this tick.
+    pub fn tick(&mut self, dirty: bool) -> bool {
+        if self.pending && dirty && self.clock.now() - self.last_input >= self.update_time {
+            self.pending = false;
+            return true;
+        }
+
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{cell::Cell, rc::Rc};
+
+    #[derive(Clone)]
+    struct MockClock {
+        now: Rc<Cell<Instant>>,
+    }
+
+    impl MockClock {
+        fn new() -> Self {
+            Self {
+                now: Rc::new(Cell::new(Instant::now())),
+            }
+        }
+
+        fn advance(&self, millis: u64) {
+            self.now.set(self.now.get() + Duration::from_millis(millis));
+        }
+    }
+
+    impl Clock for MockClock {
+        fn now(&self) -> Instant {
+            self.now.get()
+        }
+    }
+
+    #[test]
+    fn test_swap_is_written_after_update_time_of_idle_ticks() {
+        let clock = MockClock::new();
+        let mut autosave = Autosave::new(clock.clone());
+
+        autosave.input();
+        for _ in 0..15 {
+            clock.advance(250);
+            assert!(!autosave.tick(true));
+        }
+
+        clock.advance(250);
+        assert!(autosave.tick(true));
+
+        clock.advance(250);
+        assert!(!autosave.tick(true), "written once per pause in typing");
+
+        autosave.input();
+        clock.advance(4000);
+        assert!(autosave.tick(true));
+    }
+
+    #[test]
+    fn test_input_restarts_idle_time() {
+        let clock = MockClock::new();
+        let mut autosave = Autosave::new(clock.clone());
+        autosave.set_update_time(Duration::from_secs(1));
+
+        autosave.input();
+        clock.advance(900);
+        autosave.input();
+        clock.advance(900);
+        assert!(!autosave.tick(true));
+
+        clock.advance(100);
+        assert!(
+            !autosave.tick(false),
+            "a clean document has nothing to save"
+        );
+        assert!(autosave.tick(true));
+    }
+}
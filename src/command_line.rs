@@ -8,25 +8,17 @@ use crate::{
         FrameBuffer,
     },
 };
+use unicode_segmentation::UnicodeSegmentation;
 
 const PROMPT_SYMBOL: &str = ":";
 
+#[derive(Default)]
 pub struct CommandLine {
     row: Row,
     viewport: Rect,
     cursor_position: Position,
 }
 
-impl Default for CommandLine {
-    fn default() -> Self {
-        Self {
-            row: Row::default(),
-            viewport: Rect::default(),
-            cursor_position: Position::default(),
-        }
-    }
-}
-
 impl CommandLine {
     pub fn new(viewport: Rect) -> Self {
         Self {
@@ -67,15 +59,11 @@ impl CommandLine {
                 self.row.insert(self.cursor_position.x, ch);
                 self.cursor_position.x = self.cursor_position.x.saturating_add(1);
             }
-            Command::MoveCursorLeft(n) => {
-                if self.cursor_position.x > 1 {
-                    self.cursor_position.x = self.cursor_position.x.saturating_sub(n)
-                }
+            Command::MoveCursorLeft(n) if self.cursor_position.x > 1 => {
+                self.cursor_position.x = self.cursor_position.x.saturating_sub(n);
             }
-            Command::MoveCursorRight(n) => {
-                if self.cursor_position.x != self.row.len() {
-                    self.cursor_position.x = self.cursor_position.x.saturating_add(n)
-                }
+            Command::MoveCursorRight(n) if self.cursor_position.x != self.row.len() => {
+                self.cursor_position.x = self.cursor_position.x.saturating_add(n);
             }
             Command::MoveCursorLineStart => self.cursor_position.x = 1,
             Command::MoveCursorLineEnd => self.cursor_position.x = self.row.len(),
@@ -94,12 +82,57 @@ impl CommandLine {
                     return Some(Command::EnterMode(crate::editor::Mode::Normal));
                 }
             }
+            Command::DeleteWordBackward => {
+                let start = self.previous_word_start();
+                self.delete_back_to(start);
+            }
+            Command::DeleteToLineStart => self.delete_back_to(PROMPT_SYMBOL.len()),
             _ => {}
-        };
+        }
 
         None
     }
 
+    /// The index of the start of the word before the cursor, skipping any whitespace between
+    /// them. A word is either a run of keyword characters or a run of other non-blank
+    /// characters, so `/a/b/c` is deleted one part at a time. Never moves past the prompt.
+    fn previous_word_start(&self) -> usize {
+        let graphemes: Vec<&str> = self
+            .row
+            .as_str()
+            .graphemes(true)
+            .take(self.cursor_position.x)
+            .collect();
+        let is_blank = |grapheme: &str| grapheme.chars().all(char::is_whitespace);
+        let is_keyword =
+            |grapheme: &str| grapheme.chars().all(|ch| ch.is_alphanumeric() || ch == '_');
+
+        let mut start = graphemes.len();
+        while start > PROMPT_SYMBOL.len() && is_blank(graphemes[start - 1]) {
+            start -= 1;
+        }
+
+        if start > PROMPT_SYMBOL.len() {
+            let keyword = is_keyword(graphemes[start - 1]);
+
+            while start > PROMPT_SYMBOL.len()
+                && !is_blank(graphemes[start - 1])
+                && is_keyword(graphemes[start - 1]) == keyword
+            {
+                start -= 1;
+            }
+        }
+
+        start
+    }
+
+    fn delete_back_to(&mut self, start: usize) {
+        while self.cursor_position.x > start {
+            self.cursor_position.x -= 1;
+            self.row.delete(self.cursor_position.x);
+        }
+    }
+
     pub fn start_prompt(&mut self) {
         self.row = Row::from(PROMPT_SYMBOL);
         self.cursor_position.x = self.row.len();
@@ -116,6 +149,55 @@ impl CommandLine {
 
 impl Component for CommandLine {
     fn render(&self, buffer: &mut FrameBuffer) {
-        buffer.write_line(self.viewport.top(), &self.row.contents(), &Style::default());
+        buffer.write_line(
+            self.viewport.top(),
+            &self.row.to_string(0, self.viewport.width, 1),
+            &Style::default(),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn command_line_with_input(input: &str) -> CommandLine {
+        let mut command_line = CommandLine::new(Rect::new(80, 1));
+        command_line.start_prompt();
+
+        for ch in input.chars() {
+            command_line.matched_command_for(Key::Char(ch));
+        }
+
+        command_line
+    }
+
+    #[test]
+    fn test_ctrl_w_deletes_last_word() {
+        let mut command_line = command_line_with_input("w /a/b/c");
+
+        command_line.matched_command_for(Key::Ctrl('w'));
+        assert_eq!(command_line.row.as_str(), ":w /a/b/");
+
+        command_line.matched_command_for(Key::Ctrl('w'));
+        assert_eq!(command_line.row.as_str(), ":w /a/b");
+
+        let mut command_line = command_line_with_input("w  foo ");
+        command_line.matched_command_for(Key::Ctrl('w'));
+        assert_eq!(command_line.row.as_str(), ":w  ");
+
+        command_line.matched_command_for(Key::Ctrl('w'));
+        command_line.matched_command_for(Key::Ctrl('w'));
+        assert_eq!(command_line.row.as_str(), ":");
+        assert_eq!(command_line.cursor_position.x, 1);
+    }
+
+    #[test]
+    fn test_ctrl_u_clears_input() {
+        let mut command_line = command_line_with_input("set tw=40");
+
+        assert_eq!(command_line.matched_command_for(Key::Ctrl('u')), None);
+        assert_eq!(command_line.row.as_str(), ":");
+        assert_eq!(command_line.cursor_position.x, 1);
     }
 }
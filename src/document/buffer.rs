@@ -1,28 +1,59 @@
+use super::fold::{Fold, Folds};
 use crate::{
-    document::Document,
-    ops::Command,
+    document::{Change, Document, Encoding, FileType, Options, Row},
+    ops::{Command, LineRange, Operator, Setting},
+    shell,
     ui::{
-        layout::{Component, Position, Rect},
-        style::Style,
+        layout::{Component, Position, Rect, Span},
+        style::{Color, Style},
         FrameBuffer,
     },
 };
 use anyhow::{Context, Result};
+use std::{io, ops::Range};
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Keys recorded in the last inserted text that change the line rather than type a
+/// character, stored as the control characters the keys send.
+const INDENT_KEY: char = '\u{14}';
+const DEDENT_KEY: char = '\u{4}';
+const BACKSPACE_KEY: char = '\u{8}';
 
 pub struct Buffer {
     document: Document,
     viewport: Rect,
     cursor_position: Position,
+    desired_column: usize,
     offset: Position,
+    options: Options,
+    messages: Vec<String>,
+    folds: Folds,
+    /// Edits made to the document that have been applied to the folds but not yet taken.
+    changes: Vec<Change>,
+    /// The text typed so far in the current insert session, if there is one. Indenting and
+    /// dedenting the line are recorded as the Ctrl-t and Ctrl-d keys.
+    insert_recording: Option<String>,
+    last_inserted: String,
 }
 
 impl Buffer {
     pub fn new(document: Document, viewport: Rect) -> Self {
+        Self::with_options(document, viewport, Options::default())
+    }
+
+    pub fn with_options(document: Document, viewport: Rect, options: Options) -> Self {
         Self {
             document,
             viewport,
             cursor_position: Position::default(),
+            desired_column: 0,
             offset: Position::default(),
+            options,
+            messages: Vec::new(),
+            folds: Folds::default(),
+            changes: Vec::new(),
+            insert_recording: None,
+            last_inserted: String::new(),
         }
     }
 
@@ -33,78 +64,427 @@ impl Buffer {
             .clone()
     }
 
+    /// The position of the cursor within the document, regardless of scrolling.
+    pub fn document_cursor_position(&self) -> Position {
+        self.cursor_position
+    }
+
+    /// The position of the cursor on screen, after scrolling and wrapping.
     pub fn cursor_position(&self) -> Position {
+        if self.options.wrap {
+            let (line, column) = self.wrapped_cursor();
+
+            return Position::new(
+                self.viewport.left() + column,
+                self.viewport.top()
+                    + self.display_lines_between(self.offset.y, self.cursor_position.y)
+                    + line,
+            );
+        }
+
         Position::new(
-            self.cursor_position.x.saturating_sub(self.offset.x),
-            self.cursor_position.y.saturating_sub(self.offset.y),
+            self.viewport.left() + self.cursor_column().saturating_sub(self.offset.x),
+            self.viewport.top() + self.display_lines_between(self.offset.y, self.cursor_position.y),
         )
     }
 
+    pub fn set_viewport(&mut self, viewport: Rect) {
+        self.viewport = viewport;
+        self.scroll();
+    }
+
+    pub fn write_swap(&self) -> Result<(), io::Error> {
+        self.document.write_swap()
+    }
+
+    pub fn remove_swap(&self) -> Result<(), io::Error> {
+        self.document.remove_swap()
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        self.document.is_dirty()
+    }
+
+    pub fn encoding(&self) -> Encoding {
+        self.document.encoding()
+    }
+
+    pub fn file_type(&self) -> FileType {
+        self.document.file_type()
+    }
+
     pub fn lines_in_document(&self) -> usize {
         self.document.len()
     }
 
+    /// Starts recording the text typed until the insert session is finished.
+    pub fn start_insert(&mut self) {
+        self.insert_recording = Some(String::new());
+    }
+
+    /// Keeps the text typed during the insert session as the last inserted text.
+    pub fn finish_insert(&mut self) {
+        if let Some(text) = self.insert_recording.take() {
+            self.last_inserted = text;
+        }
+    }
+
+    /// The text typed during the last finished insert session.
+    pub fn last_inserted(&self) -> &str {
+        &self.last_inserted
+    }
+
+    /// Takes the edits made to the document since the last call.
+    pub fn drain_changes(&mut self) -> Vec<Change> {
+        self.sync_folds();
+        std::mem::take(&mut self.changes)
+    }
+
+    /// Moves the folds to follow the rows inserted or removed since they were last synced.
+    fn sync_folds(&mut self) {
+        for change in self.document.drain_changes() {
+            self.folds.apply(&change);
+            self.changes.push(change);
+        }
+    }
+
+    /// Takes the messages for the user reported by the commands processed since the last call.
+    pub fn drain_messages(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.messages)
+    }
+
+    fn report_written(&mut self) {
+        let summary = self.file_summary();
+        self.messages.push(format!("{summary} written"));
+    }
+
+    /// The name and size of the document, as reported after saving and by `Ctrl-g`.
+    fn file_summary(&self) -> String {
+        format!(
+            "\"{}\"{} {}L, {}B",
+            self.document_name(),
+            if self.is_dirty() { " [Modified]" } else { "" },
+            self.lines_in_document(),
+            self.document.byte_len()
+        )
+    }
+
     pub fn proccess_command(&mut self, command: Command) -> Result<()> {
         match command {
+            Command::Repeat(n, command) => {
+                for _ in 0..n {
+                    self.proccess_command((*command).clone())?;
+                }
+            }
             Command::InsertChar(ch) => {
-                self.document
+                let position = self
+                    .document
                     .insert(&self.cursor_position, ch)
                     .context("unable to insert character in document")?;
 
-                self.move_cursor(Command::MoveCursorRight(1))
-                    .context("unable to move cursor to the right")?;
-            }
-            Command::InsertLineBreak => {
-                self.document.insert_newline(&self.cursor_position);
-                self.move_cursor(Command::MoveCursorDown(1))
-                    .context("unable to move to new line")?;
-                self.move_cursor(Command::MoveCursorLineStart)
-                    .context("unable to move to start of new line")?;
+                self.set_cursor_position(position);
+                self.record_insert(|text| text.push(ch));
             }
+            Command::InsertTab => self.insert_tab()?,
+            Command::InsertLastInserted => self.insert_last_inserted()?,
+            Command::InsertLineBreak => self.insert_line_break(),
             Command::DeleteCharForward => self.document.delete(&self.cursor_position),
+            Command::DeleteCharUnderCursor => self.delete_char_under_cursor(),
+            Command::DeleteLine => {
+                let Position { y, .. } = self.cursor_position;
+
+                if self.document.delete_row(y) {
+                    let y = y.min(self.document.len().saturating_sub(1));
+                    self.set_cursor_position(Position::new(0, y));
+                }
+            }
             Command::DeleteCharBackward => {
-                if self.cursor_position.x > 0 || self.cursor_position.y > 0 {
-                    self.move_cursor(Command::MoveCursorLeft(1))
-                        .context("unable to move cursor to the left")?;
-                    self.document.delete(&self.cursor_position);
+                let position = self.document.delete_backward(&self.cursor_position);
+                self.set_cursor_position(position);
+                self.record_insert(|text| {
+                    // Forgetting a character typed in this session undoes it, but a backspace
+                    // over anything else (a shift, or text from before the insert) has to be
+                    // replayed.
+                    if text.is_empty() || text.ends_with([INDENT_KEY, DEDENT_KEY, BACKSPACE_KEY]) {
+                        text.push(BACKSPACE_KEY);
+                    } else {
+                        text.pop();
+                    }
+                });
+            }
+            Command::IndentLine => self.shift_line(true),
+            Command::DedentLine => self.shift_line(false),
+            Command::JoinLines => {
+                if let Some(position) = self.document.join_lines(self.cursor_position.y) {
+                    self.set_cursor_position(position);
                 }
             }
 
-            Command::Save => self
-                .document
-                .save(None)
-                .context("unable to save document")?,
-            Command::SaveAs(filename) => self
-                .document
-                .save(Some(&filename))
-                .context("unable to save document")?,
+            Command::ToggleFold => self.toggle_fold(),
+            Command::DeleteFold => {
+                self.folds.delete(self.cursor_position.y);
+            }
+            Command::Global(pattern, command) => self.global(&pattern, false, &command)?,
+            Command::InvertedGlobal(pattern, command) => self.global(&pattern, true, &command)?,
+            Command::Filter(range, command) => self.filter(range, &command),
+            Command::Operate(operator, motion) => self
+                .operate(operator, &motion)
+                .context("unable to apply operator")?,
+            Command::Set(setting) => self.set(setting),
+
+            Command::Save => {
+                self.document.save().context("unable to save document")?;
+                self.report_written();
+            }
+            Command::SaveIfModifiedAndQuit => {
+                if self.document.is_dirty() {
+                    self.document.save().context("unable to save document")?;
+                    self.report_written();
+                }
+            }
+            Command::SaveAs(filename) => {
+                self.document
+                    .save_as(&filename)
+                    .context("unable to save document")?;
+                self.report_written();
+            }
+            Command::SetFileName(file_name) => {
+                self.document.set_file_name(&file_name);
+                let summary = self.file_summary();
+                self.messages.push(summary);
+            }
+            Command::ShowFileInfo => {
+                let summary = self.file_summary();
+                self.messages.push(summary);
+            }
             _ => {
-                self.move_cursor(command).context("unable to move cursor")?;
+                self.move_cursor(&command)
+                    .context("unable to move cursor")?;
+            }
+        }
+
+        self.sync_folds();
+        self.scroll();
+
+        Ok(())
+    }
+
+    fn operate(&mut self, operator: Operator, motion: &Command) -> Result<()> {
+        let origin = self.cursor_position;
+        self.move_cursor(motion)
+            .context("unable to move cursor over operator range")?;
+
+        let span = Span::new(origin, self.cursor_position).normalized();
+        let start = span.start.y;
+        let end = span.end.y + 1;
+
+        match operator {
+            Operator::Reflow => {
+                self.document
+                    .reflow_range(start..end, self.options.text_width);
+            }
+            Operator::Fold => self.folds.create(start, end),
+            Operator::ToggleComment => {
+                let token = self.document.comment_token();
+                self.document.toggle_comment_range(start..end, token);
             }
+        }
+
+        self.set_cursor_position(Position::new(0, start));
+
+        Ok(())
+    }
+
+    /// Deletes the grapheme under the cursor. Unlike Delete in insert mode, `x` never joins
+    /// the next line onto this one when the cursor is at the end of the row.
+    fn delete_char_under_cursor(&mut self) {
+        let Position { x, y } = self.cursor_position;
+
+        if x < self.document.row(y).map_or(0, Row::len) {
+            self.document.delete(&self.cursor_position);
+        }
+    }
+
+    /// Splits the row at the cursor, moving the cursor to the start of the new row.
+    fn insert_line_break(&mut self) {
+        if let Some(position) = self.document.insert_newline(&self.cursor_position) {
+            self.set_cursor_position(position);
+            self.record_insert(|text| text.push('\n'));
+        }
+    }
+
+    fn record_insert<F>(&mut self, f: F)
+    where
+        F: FnOnce(&mut String),
+    {
+        if let Some(text) = &mut self.insert_recording {
+            f(text);
+        }
+    }
+
+    /// Types the last inserted text again at the cursor.
+    fn insert_last_inserted(&mut self) -> Result<()> {
+        let text = self.last_inserted().to_string();
+
+        for ch in text.chars() {
+            self.proccess_command(match ch {
+                '\n' => Command::InsertLineBreak,
+                INDENT_KEY => Command::IndentLine,
+                DEDENT_KEY => Command::DedentLine,
+                BACKSPACE_KEY => Command::DeleteCharBackward,
+                ch => Command::InsertChar(ch),
+            })?;
+        }
+
+        Ok(())
+    }
+
+    /// Indents (or dedents) the cursor's row by `shiftwidth`, keeping the cursor on the same
+    /// character. Without `expandtab` the indentation is made of tabs where it can be.
+    fn shift_line(&mut self, indent: bool) {
+        let Position { x, y } = self.cursor_position;
+        let Options {
+            shift_width,
+            tab_stop,
+            expand_tab,
+            ..
+        } = self.options;
+
+        let x = if !indent {
+            x.saturating_sub(self.document.dedent_row(y, shift_width))
+        } else if expand_tab {
+            x + self.document.indent_row(y, shift_width)
+        } else {
+            self.document
+                .indent_row_with_tabs(y, shift_width, tab_stop)
+                .map_or(x, |(before, after)| x.saturating_sub(before) + after)
         };
 
-        self.scroll().context("unable to scroll buffer")?;
+        self.set_cursor_position(Position::new(x, y));
+        self.record_insert(|text| text.push(if indent { INDENT_KEY } else { DEDENT_KEY }));
+    }
+
+    /// Inserts a tab, or with `expandtab` the number of spaces up to the next tab stop.
+    fn insert_tab(&mut self) -> Result<()> {
+        if !self.options.expand_tab {
+            return self.proccess_command(Command::InsertChar('\t'));
+        }
+
+        let tab_stop = self.options.tab_stop.max(1);
+        let spaces = tab_stop - self.cursor_column() % tab_stop;
+
+        self.proccess_command(Command::Repeat(spaces, Box::new(Command::InsertChar(' '))))
+    }
+
+    /// Closes the fold at the cursor, moving the cursor to its first row, or opens it when it
+    /// is already closed.
+    fn toggle_fold(&mut self) {
+        if let Some(Fold {
+            start,
+            closed: true,
+            ..
+        }) = self.folds.toggle(self.cursor_position.y)
+        {
+            self.set_cursor_position(Position::new(0, start));
+        }
+    }
+
+    /// Pipes the lines through the shell command, replacing them with its output and moving
+    /// the cursor to the first of them. The lines are left untouched when the command fails
+    /// or the range is not in the document.
+    fn filter(&mut self, range: LineRange, command: &str) {
+        let Some(rows) = self.rows_in(range) else {
+            self.messages.push("E16: Invalid range".into());
+            return;
+        };
+
+        let mut input = String::new();
+        for row in self.document.lines_in(rows.clone()) {
+            input.push_str(row.as_str());
+            input.push('\n');
+        }
+
+        match shell::filter(command, &input) {
+            Ok(output) => {
+                self.document.replace_rows(rows.clone(), &output);
+                let y = rows.start.min(self.document.len().saturating_sub(1));
+                self.set_cursor_position(Position::new(0, y));
+            }
+            Err(e) => self.messages.push(e.to_string()),
+        }
+    }
+
+    /// The rows an ex command's line range covers, or `None` when it names a line number
+    /// outside the document.
+    fn rows_in(&self, range: LineRange) -> Option<Range<usize>> {
+        match range {
+            LineRange::Current => Some(self.cursor_position.y..self.cursor_position.y + 1),
+            LineRange::All => Some(0..self.document.len()),
+            LineRange::Lines(start, end) => {
+                let (first, last) = (start.min(end), start.max(end));
+                (first >= 1 && last <= self.document.len()).then(|| first - 1..last)
+            }
+        }
+    }
+
+    /// Runs the command on each row containing the pattern (or not containing it, when
+    /// inverted), starting from the last so that deleting a row does not shift the rows that
+    /// are still to be visited.
+    fn global(&mut self, pattern: &str, inverted: bool, command: &Command) -> Result<()> {
+        let rows = self.document.matching_rows(pattern, inverted);
+
+        for &y in rows.iter().rev() {
+            self.set_cursor_position(Position::new(0, y));
+            self.proccess_command(command.clone())?;
+        }
 
         Ok(())
     }
 
-    fn move_cursor(&mut self, command: Command) -> Result<()> {
+    fn set(&mut self, setting: Setting) {
+        match setting {
+            Setting::FileEncoding(encoding) => self.document.set_encoding(encoding),
+            _ => self.options.apply(setting),
+        }
+    }
+
+    #[allow(clippy::unnecessary_wraps)]
+    fn move_cursor(&mut self, command: &Command) -> Result<()> {
         use crate::document::Row;
 
-        let terminal_height = self.viewport.height - 2;
+        match *command {
+            Command::Repeat(n, ref motion) => {
+                for _ in 0..n {
+                    self.move_cursor(motion)?;
+                }
+
+                return Ok(());
+            }
+            Command::MoveCursorDisplayLineDown(n) if !self.options.wrap => {
+                return self.move_cursor(&Command::MoveCursorDown(n));
+            }
+            Command::MoveCursorDisplayLineUp(n) if !self.options.wrap => {
+                return self.move_cursor(&Command::MoveCursorUp(n));
+            }
+            Command::MoveCursorDisplayLineDown(n) => {
+                (0..n).for_each(|_| self.move_cursor_display_line(true));
+                return Ok(());
+            }
+            Command::MoveCursorDisplayLineUp(n) => {
+                (0..n).for_each(|_| self.move_cursor_display_line(false));
+                return Ok(());
+            }
+            _ => {}
+        }
+
+        let terminal_height = self.viewport.height;
         let Position { x, y } = self.cursor_position;
         let height = self.document.len();
         let width = self.document.row(y).map_or(0, Row::len);
 
-        let (x, y) = match command {
-            Command::MoveCursorUp(n) => (x, y.saturating_sub(n)),
-            Command::MoveCursorDown(n) => {
-                if y < height {
-                    (x, y.saturating_add(n))
-                } else {
-                    (x, y)
-                }
-            }
+        let (x, y) = match *command {
+            Command::MoveCursorUp(n) => (x, (0..n).fold(y, |y, _| self.previous_row(y))),
+            Command::MoveCursorDown(n) => (x, (0..n).fold(y, |y, _| self.next_row(y))),
             Command::MoveCursorLeft(n) => {
                 if x > 0 {
                     (x - n, y)
@@ -119,7 +499,7 @@ impl Buffer {
             Command::MoveCursorRight(n) => {
                 if x < width {
                     (x + n, y)
-                } else if y < height {
+                } else if y + n < height {
                     (0, y + n)
                 } else {
                     (x, y)
@@ -136,7 +516,7 @@ impl Buffer {
                 if y.saturating_add(terminal_height) < height {
                     (x, y + terminal_height)
                 } else {
-                    (x, height)
+                    (x, height.saturating_sub(1))
                 }
             }
             Command::MoveCursorLineStart => (0, y),
@@ -144,6 +524,17 @@ impl Buffer {
             _ => (x, y),
         };
 
+        let vertical = matches!(
+            command,
+            Command::MoveCursorUp(_)
+                | Command::MoveCursorDown(_)
+                | Command::MoveCursorPageUp
+                | Command::MoveCursorPageDown
+        );
+
+        // Vertical motion aims for the column the cursor was last horizontally placed at so
+        // that moving through a short line does not lose the column on longer lines.
+        let x = if vertical { self.desired_column } else { x };
         let new_width = self.document.row(y).map_or(0, Row::len);
 
         self.cursor_position = Position {
@@ -151,47 +542,1122 @@ impl Buffer {
             y,
         };
 
+        if !vertical {
+            self.desired_column = if let Command::MoveCursorLineEnd = command {
+                usize::MAX
+            } else {
+                self.cursor_position.x
+            };
+        }
+
         Ok(())
     }
 
-    pub fn scroll(&mut self) -> Result<()> {
-        let Position { x, y } = self.cursor_position;
-        let width = self.viewport.width;
-        let height = self.viewport.height - 2;
+    /// Moves the cursor to the same column on the next (or previous) wrapped display line,
+    /// which may be part of the same row.
+    fn move_cursor_display_line(&mut self, down: bool) {
+        let width = self.viewport.width.max(1);
+        let column = self.cursor_column();
+        let y = self.cursor_position.y;
+        let display_width = |y: usize| {
+            self.document
+                .row(y)
+                .map_or(0, |row| row.display_width(self.options.tab_stop))
+        };
+        // A closed fold is a single display line however long its first row is.
+        let unfolded = |y: usize| self.folds.closed_at(y).is_none();
 
-        let offset = if y < self.offset.y {
-            (self.offset.x, y)
-        } else if y >= self.offset.y.saturating_add(height) {
-            (self.offset.x, y.saturating_sub(height).saturating_add(1))
+        let target = if down {
+            if unfolded(y) && column / width < display_width(y).saturating_sub(1) / width {
+                Some((y, column + width))
+            } else if self.next_row(y) > y {
+                Some((self.next_row(y), column % width))
+            } else {
+                None
+            }
+        } else if unfolded(y) && column >= width {
+            Some((y, column - width))
+        } else if y > 0 {
+            let y = self.previous_row(y);
+            let last_line_start = if unfolded(y) {
+                display_width(y).saturating_sub(1) / width * width
+            } else {
+                0
+            };
+            Some((y, last_line_start + column))
         } else {
-            (self.offset.x, self.offset.y)
+            None
         };
 
-        let offset = if x < self.offset.x {
-            (x, offset.1)
-        } else if x >= self.offset.x.saturating_add(width) {
-            (x.saturating_add(width).saturating_add(1), offset.1)
+        if let Some((y, column)) = target {
+            let x = self.document.row(y).map_or(0, |row| {
+                row.grapheme_at_column(column, self.options.tab_stop)
+            });
+
+            self.set_cursor_position(Position::new(x, y));
+        }
+    }
+
+    /// The number of display lines taken up by the rows from `start` up to `end`.
+    fn display_lines_between(&self, start: usize, end: usize) -> usize {
+        (start..end).map(|y| self.display_lines_in_row(y)).sum()
+    }
+
+    /// The number of display lines the row takes up. Only the first row of a closed fold is
+    /// displayed, as a single line.
+    fn display_lines_in_row(&self, y: usize) -> usize {
+        if let Some(fold) = self.folds.closed_at(y) {
+            return usize::from(y == fold.start);
+        }
+
+        if !self.options.wrap {
+            return 1;
+        }
+
+        let width = self.viewport.width.max(1);
+
+        self.document.row(y).map_or(1, |row| {
+            let display_width = row.display_width(self.options.tab_stop);
+            display_width.div_ceil(width).max(1)
+        })
+    }
+
+    /// The display line of its row the cursor is on when wrapping, and its column within that
+    /// line. Just past the end of a row that fills its last line, the cursor stays at the end
+    /// of that line rather than going onto the next row's.
+    fn wrapped_cursor(&self) -> (usize, usize) {
+        let width = self.viewport.width.max(1);
+        let column = self.cursor_column();
+        let last_line = self
+            .display_lines_in_row(self.cursor_position.y)
+            .saturating_sub(1);
+
+        if column / width > last_line {
+            (last_line, width - 1)
         } else {
-            (self.offset.x, offset.1)
+            (column / width, column % width)
+        }
+    }
+
+    /// The row below, skipping over the rest of a closed fold.
+    fn next_row(&self, y: usize) -> usize {
+        let next = self.folds.closed_at(y).map_or(y, |fold| fold.end - 1) + 1;
+        next.min(self.document.len().saturating_sub(1))
+    }
+
+    /// The row above, landing on the start of a closed fold.
+    fn previous_row(&self, y: usize) -> usize {
+        let y = y.saturating_sub(1);
+        self.folds.closed_at(y).map_or(y, |fold| fold.start)
+    }
+
+    fn set_cursor_position(&mut self, position: Position) {
+        self.cursor_position = position;
+        self.desired_column = position.x;
+    }
+
+    /// The display column of the cursor within its row, accounting for expanded tabs.
+    fn cursor_column(&self) -> usize {
+        let Position { x, y } = self.cursor_position;
+        self.document
+            .row(y)
+            .map_or(x, |row| row.display_column(x, self.options.tab_stop))
+    }
+
+    pub fn scroll(&mut self) {
+        let x = self.cursor_column();
+        let y = self.cursor_position.y;
+        let width = self.viewport.width;
+        let height = self.viewport.height;
+        let cursor_line = if self.options.wrap {
+            self.wrapped_cursor().0
+        } else {
+            0
         };
 
-        self.offset = Position::from(offset);
+        if y < self.offset.y {
+            self.offset.y = y;
+        }
 
-        Ok(())
+        let mut lines = self.display_lines_between(self.offset.y, y);
+        while self.offset.y < y && lines + cursor_line >= height {
+            lines -= self.display_lines_in_row(self.offset.y);
+            self.offset.y += 1;
+        }
+
+        if self.options.wrap {
+            self.offset.x = 0;
+        } else if x < self.offset.x {
+            self.offset.x = x;
+        } else if x >= self.offset.x.saturating_add(width) {
+            self.offset.x = x.saturating_sub(width).saturating_add(1);
+        }
+    }
+
+    /// Draws a closed fold as the number of rows it hides followed by the text of its first
+    /// row, filling the rest of the line with dashes.
+    fn render_fold(&self, buffer: &mut FrameBuffer, line: usize, fold: &Fold, row: &Row) {
+        let width = self.viewport.width;
+        let summary = format!("+--{:>3} lines: {} ", fold.len(), row.as_str().trim());
+        let summary = Row::from(&summary[..]).to_string(0, width, self.options.tab_stop);
+        let fill = "-".repeat(width.saturating_sub(summary.graphemes(true).count()));
+
+        buffer.write_line(line, &format!("{summary}{fill}"), &fold_style());
+    }
+
+    /// Draws the display columns `start..end` of the row onto the line, highlighting any
+    /// trailing whitespace when enabled.
+    fn render_row(
+        &self,
+        buffer: &mut FrameBuffer,
+        line: usize,
+        row: &Row,
+        start: usize,
+        end: usize,
+    ) {
+        buffer.write_line(
+            line,
+            &row.to_string(start, end, self.options.tab_stop),
+            &Style::default(),
+        );
+
+        if !self.options.highlight_trailing_whitespace {
+            return;
+        }
+
+        let trimmed = row.as_str().trim_end().graphemes(true).count();
+        if trimmed == row.len() {
+            return;
+        }
+
+        let whitespace_start = row
+            .display_column(trimmed, self.options.tab_stop)
+            .max(start);
+        if whitespace_start < end {
+            buffer.write_at(
+                self.viewport.left() + whitespace_start - start,
+                line,
+                &row.to_string(whitespace_start, end, self.options.tab_stop),
+                &trailing_whitespace_style(),
+            );
+        }
     }
 }
 
+fn fold_style() -> Style {
+    Style::new(Color::Blue, Color::Gray)
+}
+
+fn trailing_whitespace_style() -> Style {
+    Style::new(Color::Reset, Color::Red)
+}
+
 impl Component for Buffer {
     fn render(&self, buffer: &mut FrameBuffer) {
-        for terminal_row in 0..self.viewport.height {
-            if let Some(row) = self.document.row(terminal_row as usize + self.offset.y) {
-                let start = self.offset.x;
-                let end = self.offset.x + self.viewport.width;
-                let row = row.to_string(start, end);
-                buffer.write_line(terminal_row, &row, &Style::default());
+        let width = self.viewport.width;
+        let mut line = self.viewport.top();
+        let rows = self.document.lines_in(self.offset.y..self.document.len());
+
+        for (index, row) in (self.offset.y..).zip(rows) {
+            if line >= self.viewport.bottom() {
+                break;
+            }
+
+            if let Some(fold) = self.folds.closed_at(index) {
+                if index == fold.start {
+                    self.render_fold(buffer, line, fold, row);
+                    line += 1;
+                }
+            } else if self.options.wrap {
+                for display_line in 0..self.display_lines_in_row(index) {
+                    if line >= self.viewport.bottom() {
+                        break;
+                    }
+
+                    let start = display_line * width;
+                    self.render_row(buffer, line, row, start, start + width);
+                    line += 1;
+                }
             } else {
-                buffer.write_line(terminal_row, "~", &Style::default());
+                let start = self.offset.x;
+                self.render_row(buffer, line, row, start, start + width);
+                line += 1;
             }
         }
+
+        while line < self.viewport.bottom() {
+            buffer.write_line(
+                line,
+                &self.options.end_of_buffer_char.to_string(),
+                &Style::default(),
+            );
+            line += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document::SaveError;
+    use std::{env, fs};
+
+    #[test]
+    fn test_save_without_file_name_is_not_reported_as_written() {
+        let mut buffer = Buffer::new(Document::from("text"), Rect::new(80, 24));
+        buffer.proccess_command(Command::InsertChar('!')).unwrap();
+
+        let error = buffer.proccess_command(Command::Save).unwrap_err();
+
+        assert!(matches!(
+            error.downcast_ref::<SaveError>(),
+            Some(SaveError::NoFileName)
+        ));
+        assert!(buffer.drain_messages().is_empty());
+        assert!(buffer.is_dirty());
+    }
+
+    #[test]
+    fn test_save_as_writes_buffer_contents() {
+        let path = env::temp_dir().join("vie_test_save_as.txt");
+        let path = path.to_str().unwrap();
+        let _ = fs::remove_file(path);
+
+        let mut buffer = Buffer::new(Document::from("first\nsecond"), Rect::new(80, 24));
+        buffer.proccess_command(Command::InsertChar('!')).unwrap();
+        assert!(buffer.is_dirty());
+
+        buffer
+            .proccess_command(Command::SaveAs(path.into()))
+            .unwrap();
+
+        assert!(!buffer.is_dirty());
+        assert_eq!(buffer.document_name(), path);
+        assert_eq!(
+            buffer.drain_messages(),
+            vec![format!("\"{path}\" 2L, 14B written")]
+        );
+        assert!(buffer.drain_messages().is_empty());
+        assert_eq!(fs::read_to_string(path).unwrap(), "!first\nsecond\n");
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_save_if_modified_only_writes_modified_buffers() {
+        let path = env::temp_dir().join("vie_test_save_if_modified.txt");
+        let path = path.to_str().unwrap();
+        fs::write(path, "on disk\n").unwrap();
+
+        let mut buffer = Buffer::new(Document::open(path).unwrap(), Rect::new(80, 24));
+        fs::write(path, "changed elsewhere\n").unwrap();
+
+        buffer
+            .proccess_command(Command::SaveIfModifiedAndQuit)
+            .unwrap();
+        assert!(buffer.drain_messages().is_empty());
+        assert_eq!(fs::read_to_string(path).unwrap(), "changed elsewhere\n");
+
+        buffer.proccess_command(Command::InsertChar('!')).unwrap();
+        buffer
+            .proccess_command(Command::SaveIfModifiedAndQuit)
+            .unwrap();
+        assert!(!buffer.is_dirty());
+        assert_eq!(
+            buffer.drain_messages(),
+            vec![format!("\"{path}\" 1L, 9B written")]
+        );
+        assert_eq!(fs::read_to_string(path).unwrap(), "!on disk\n");
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_insert_last_inserted_text() {
+        let mut buffer = Buffer::new(Document::default(), Rect::new(80, 24));
+
+        buffer.start_insert();
+        for ch in "abx".chars() {
+            buffer.proccess_command(Command::InsertChar(ch)).unwrap();
+        }
+        buffer
+            .proccess_command(Command::DeleteCharBackward)
+            .unwrap();
+        buffer.proccess_command(Command::InsertChar('c')).unwrap();
+        buffer.finish_insert();
+        assert_eq!(buffer.last_inserted(), "abc");
+
+        buffer.start_insert();
+        buffer
+            .proccess_command(Command::InsertLastInserted)
+            .unwrap();
+        buffer.finish_insert();
+
+        let rows: Vec<&str> = buffer.document.lines().map(Row::as_str).collect();
+        assert_eq!(rows, vec!["abcabc"]);
+        assert_eq!(buffer.document_cursor_position(), Position::new(6, 0));
+        assert_eq!(buffer.last_inserted(), "abc");
+    }
+
+    #[test]
+    fn test_insert_last_inserted_text_with_line_breaks() {
+        let mut buffer = Buffer::new(Document::default(), Rect::new(80, 24));
+
+        buffer.start_insert();
+        buffer.proccess_command(Command::InsertChar('a')).unwrap();
+        buffer.proccess_command(Command::InsertLineBreak).unwrap();
+        buffer.proccess_command(Command::InsertChar('b')).unwrap();
+        buffer.finish_insert();
+
+        buffer
+            .proccess_command(Command::InsertLastInserted)
+            .unwrap();
+
+        let rows: Vec<&str> = buffer.document.lines().map(Row::as_str).collect();
+        assert_eq!(rows, vec!["a", "ba", "b"]);
+        assert_eq!(buffer.last_inserted(), "a\nb");
+    }
+
+    fn line_break_buffer(contents: &str, cursor: Position) -> Buffer {
+        let mut buffer = Buffer::new(Document::from(contents), Rect::new(80, 24));
+        buffer.set_cursor_position(cursor);
+        buffer.proccess_command(Command::InsertLineBreak).unwrap();
+        buffer
+    }
+
+    #[test]
+    fn test_insert_line_break_mid_line() {
+        let buffer = line_break_buffer("first\nsecond", Position::new(3, 0));
+
+        let rows: Vec<&str> = buffer.document.lines().map(Row::as_str).collect();
+        assert_eq!(rows, vec!["fir", "st", "second"]);
+        assert_eq!(buffer.document_cursor_position(), Position::new(0, 1));
+    }
+
+    #[test]
+    fn test_insert_line_break_at_end_of_file() {
+        let buffer = line_break_buffer("first\nlast", Position::new(4, 1));
+
+        let rows: Vec<&str> = buffer.document.lines().map(Row::as_str).collect();
+        assert_eq!(rows, vec!["first", "last", ""]);
+        assert_eq!(buffer.document_cursor_position(), Position::new(0, 2));
+    }
+
+    #[test]
+    fn test_insert_line_break_in_empty_document() {
+        let buffer = line_break_buffer("", Position::new(0, 0));
+
+        let rows: Vec<&str> = buffer.document.lines().map(Row::as_str).collect();
+        assert_eq!(rows, vec!["", ""]);
+        assert_eq!(buffer.document_cursor_position(), Position::new(0, 1));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_filter_replaces_lines_with_command_output() {
+        let mut buffer = Buffer::new(Document::from("one\ntwo\nthree"), Rect::new(80, 24));
+
+        buffer
+            .proccess_command(Command::Filter(LineRange::Lines(2, 3), "tr a-z A-Z".into()))
+            .unwrap();
+
+        let rows: Vec<&str> = buffer.document.lines().map(Row::as_str).collect();
+        assert_eq!(rows, vec!["one", "TWO", "THREE"]);
+        assert_eq!(buffer.document_cursor_position(), Position::new(0, 1));
+        assert!(buffer.is_dirty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_failed_filter_leaves_lines_unchanged() {
+        let mut buffer = Buffer::new(Document::from("one\ntwo"), Rect::new(80, 24));
+
+        buffer
+            .proccess_command(Command::Filter(
+                LineRange::All,
+                "cat > /dev/null; exit 1".into(),
+            ))
+            .unwrap();
+
+        let rows: Vec<&str> = buffer.document.lines().map(Row::as_str).collect();
+        assert_eq!(rows, vec!["one", "two"]);
+        assert!(!buffer.is_dirty());
+        assert_eq!(buffer.drain_messages(), vec!["shell returned 1"]);
+    }
+
+    #[test]
+    fn test_filter_rejects_lines_outside_document() {
+        let mut buffer = Buffer::new(Document::from("one\ntwo"), Rect::new(80, 24));
+
+        for range in [LineRange::Lines(0, 0), LineRange::Lines(2, 3)] {
+            buffer
+                .proccess_command(Command::Filter(range, "tr a-z A-Z".into()))
+                .unwrap();
+
+            assert_eq!(buffer.drain_messages(), vec!["E16: Invalid range"]);
+        }
+
+        let rows: Vec<&str> = buffer.document.lines().map(Row::as_str).collect();
+        assert_eq!(rows, vec!["one", "two"]);
+        assert!(!buffer.is_dirty());
+    }
+
+    fn shift_width_buffer(contents: &str) -> Buffer {
+        let mut buffer = Buffer::new(Document::from(contents), Rect::new(80, 24));
+        buffer
+            .proccess_command(Command::Set(Setting::ShiftWidth(4)))
+            .unwrap();
+        buffer
+    }
+
+    #[test]
+    fn test_indent_line_keeps_cursor_on_same_character() {
+        let mut buffer = shift_width_buffer("foo");
+        buffer
+            .proccess_command(Command::MoveCursorRight(2))
+            .unwrap();
+
+        buffer.proccess_command(Command::IndentLine).unwrap();
+
+        assert_eq!(buffer.document.row(0).unwrap().as_str(), "    foo");
+        assert_eq!(buffer.cursor_position(), Position::new(6, 0));
+    }
+
+    #[test]
+    fn test_indent_line_without_expand_tab_uses_tabs() {
+        let mut buffer = shift_width_buffer("  foo");
+        for setting in [Setting::ExpandTab(false), Setting::TabStop(4)] {
+            buffer.proccess_command(Command::Set(setting)).unwrap();
+        }
+        buffer
+            .proccess_command(Command::MoveCursorRight(3))
+            .unwrap();
+
+        buffer.proccess_command(Command::IndentLine).unwrap();
+
+        assert_eq!(buffer.document.row(0).unwrap().as_str(), "\t  foo");
+        assert_eq!(buffer.document_cursor_position(), Position::new(4, 0));
+        assert_eq!(buffer.cursor_position(), Position::new(7, 0));
+    }
+
+    #[test]
+    fn test_dedent_line_keeps_cursor_on_same_character() {
+        let mut buffer = shift_width_buffer("      foo");
+        buffer
+            .proccess_command(Command::MoveCursorRight(7))
+            .unwrap();
+
+        buffer.proccess_command(Command::DedentLine).unwrap();
+        assert_eq!(buffer.document.row(0).unwrap().as_str(), "  foo");
+        assert_eq!(buffer.cursor_position(), Position::new(3, 0));
+
+        buffer.proccess_command(Command::DedentLine).unwrap();
+        assert_eq!(buffer.document.row(0).unwrap().as_str(), "foo");
+        assert_eq!(buffer.cursor_position(), Position::new(1, 0));
+    }
+
+    #[test]
+    fn test_insert_last_inserted_replays_indent() {
+        let mut buffer = shift_width_buffer("foo\nbar");
+
+        buffer.start_insert();
+        buffer.proccess_command(Command::IndentLine).unwrap();
+        buffer.proccess_command(Command::InsertChar('!')).unwrap();
+        buffer.finish_insert();
+        assert_eq!(buffer.last_inserted(), "\u{14}!");
+
+        buffer.set_cursor_position(Position::new(0, 1));
+        buffer
+            .proccess_command(Command::InsertLastInserted)
+            .unwrap();
+
+        let rows: Vec<&str> = buffer.document.lines().map(Row::as_str).collect();
+        assert_eq!(rows, vec!["    !foo", "    !bar"]);
+        assert_eq!(buffer.cursor_position(), Position::new(5, 1));
+    }
+
+    #[test]
+    fn test_insert_last_inserted_replays_backspace_over_existing_text() {
+        let mut buffer = Buffer::new(Document::from("foo\nbar"), Rect::new(80, 24));
+        buffer.proccess_command(Command::MoveCursorLineEnd).unwrap();
+
+        buffer.start_insert();
+        buffer
+            .proccess_command(Command::DeleteCharBackward)
+            .unwrap();
+        buffer.proccess_command(Command::InsertChar('x')).unwrap();
+        buffer.finish_insert();
+        assert_eq!(buffer.last_inserted(), "\u{8}x");
+
+        buffer.set_cursor_position(Position::new(3, 1));
+        buffer
+            .proccess_command(Command::InsertLastInserted)
+            .unwrap();
+
+        let rows: Vec<&str> = buffer.document.lines().map(Row::as_str).collect();
+        assert_eq!(rows, vec!["fox", "bax"]);
+    }
+
+    #[test]
+    fn test_insert_last_inserted_replays_backspace_after_dedent() {
+        let mut buffer = shift_width_buffer("        foo");
+        buffer.proccess_command(Command::MoveCursorLineEnd).unwrap();
+
+        buffer.start_insert();
+        buffer.proccess_command(Command::DedentLine).unwrap();
+        buffer
+            .proccess_command(Command::DeleteCharBackward)
+            .unwrap();
+        buffer.finish_insert();
+        assert_eq!(buffer.last_inserted(), "\u{4}\u{8}");
+
+        buffer
+            .proccess_command(Command::InsertLastInserted)
+            .unwrap();
+
+        assert_eq!(buffer.document.row(0).unwrap().as_str(), "f");
+    }
+
+    #[test]
+    fn test_vertical_motion_restores_desired_column() {
+        let mut buffer = Buffer::new(
+            Document::from("a long line\nab\nanother long line"),
+            Rect::new(80, 24),
+        );
+        buffer
+            .proccess_command(Command::MoveCursorRight(6))
+            .unwrap();
+
+        buffer.proccess_command(Command::MoveCursorDown(1)).unwrap();
+        assert_eq!(buffer.cursor_position(), Position::new(2, 1));
+
+        buffer.proccess_command(Command::MoveCursorDown(1)).unwrap();
+        assert_eq!(buffer.cursor_position(), Position::new(6, 2));
+
+        buffer.proccess_command(Command::MoveCursorUp(2)).unwrap();
+        assert_eq!(buffer.cursor_position(), Position::new(6, 0));
+
+        buffer.proccess_command(Command::MoveCursorLineEnd).unwrap();
+        buffer.proccess_command(Command::MoveCursorDown(2)).unwrap();
+        assert_eq!(buffer.cursor_position(), Position::new(17, 2));
+    }
+
+    #[test]
+    fn test_backspace_at_line_start_joins_with_previous_line() {
+        let mut buffer = Buffer::new(Document::from("abc\ndef"), Rect::new(80, 24));
+        buffer.proccess_command(Command::MoveCursorDown(1)).unwrap();
+
+        buffer
+            .proccess_command(Command::DeleteCharBackward)
+            .unwrap();
+
+        assert_eq!(buffer.lines_in_document(), 1);
+        assert_eq!(buffer.document.row(0).unwrap().as_str(), "abcdef");
+        assert_eq!(buffer.cursor_position(), Position::new(3, 0));
+
+        buffer
+            .proccess_command(Command::DeleteCharBackward)
+            .unwrap();
+        assert_eq!(buffer.document.row(0).unwrap().as_str(), "abdef");
+        assert_eq!(buffer.cursor_position(), Position::new(2, 0));
+    }
+
+    #[test]
+    fn test_cursor_position_accounts_for_tabs() {
+        let mut buffer = Buffer::new(Document::from("\tab"), Rect::new(80, 24));
+        buffer
+            .proccess_command(Command::Set(Setting::TabStop(8)))
+            .unwrap();
+        buffer
+            .proccess_command(Command::MoveCursorRight(2))
+            .unwrap();
+
+        assert_eq!(buffer.cursor_position(), Position::new(9, 0));
+    }
+
+    #[test]
+    fn test_horizontal_scroll_keeps_cursor_in_view() {
+        let mut buffer = Buffer::new(Document::from(&"x".repeat(30)[..]), Rect::new(10, 5));
+        buffer
+            .proccess_command(Command::MoveCursorRight(25))
+            .unwrap();
+
+        assert_eq!(buffer.cursor_position(), Position::new(9, 0));
+    }
+
+    fn rendered_lines(buffer: &Buffer) -> Vec<usize> {
+        let area = Rect::new(buffer.viewport.width, buffer.viewport.height);
+        let mut frame = FrameBuffer::empty(area);
+        buffer.render(&mut frame);
+
+        let mut lines: Vec<usize> = FrameBuffer::empty(area)
+            .diff(&frame)
+            .into_iter()
+            .map(|cell| cell.position().y)
+            .collect();
+        lines.dedup();
+        lines
+    }
+
+    #[test]
+    fn test_document_and_screen_cursor_positions_after_scrolling() {
+        let contents = (0..20).map(|i| format!("line {i}")).collect::<Vec<_>>();
+        let mut buffer = Buffer::new(
+            Document::from(&contents.join("\n")[..]),
+            Rect::positioned(4, 5, 0, 2),
+        );
+
+        buffer
+            .proccess_command(Command::MoveCursorDown(12))
+            .unwrap();
+        buffer
+            .proccess_command(Command::MoveCursorRight(6))
+            .unwrap();
+
+        assert_eq!(buffer.document_cursor_position(), Position::new(6, 12));
+        assert_eq!(buffer.cursor_position(), Position::new(3, 6));
+    }
+
+    #[test]
+    fn test_end_of_buffer_fill() {
+        let mut buffer = Buffer::new(Document::from("one\ntwo"), Rect::new(10, 5));
+        assert_eq!(rendered_lines(&buffer), vec![0, 1, 2, 3, 4]);
+
+        buffer
+            .proccess_command(Command::Set(Setting::EndOfBufferChar(' ')))
+            .unwrap();
+        assert_eq!(rendered_lines(&buffer), vec![0, 1]);
+    }
+
+    fn folded_buffer() -> Buffer {
+        let mut buffer = Buffer::new(Document::from("0\n1\n2\n3\n4"), Rect::new(20, 5));
+        buffer.proccess_command(Command::MoveCursorDown(1)).unwrap();
+        buffer
+            .proccess_command(Command::Operate(
+                Operator::Fold,
+                Box::new(Command::MoveCursorDown(2)),
+            ))
+            .unwrap();
+        buffer
+    }
+
+    fn rendered_text(buffer: &Buffer) -> Vec<String> {
+        let mut frame =
+            FrameBuffer::empty(Rect::new(buffer.viewport.width, buffer.viewport.height));
+        buffer.render(&mut frame);
+
+        frame.to_string().split('\n').map(String::from).collect()
+    }
+
+    #[test]
+    fn test_manual_fold_renders_summary() {
+        let buffer = folded_buffer();
+
+        assert_eq!(
+            rendered_text(&buffer),
+            vec!["0", "+--  3 lines: 1 ----", "4", "~", "~"]
+        );
+        assert_eq!(buffer.cursor_position(), Position::new(0, 1));
+    }
+
+    #[test]
+    fn test_closed_fold_is_one_line_for_motion() {
+        let mut buffer = folded_buffer();
+
+        buffer.proccess_command(Command::MoveCursorDown(1)).unwrap();
+        assert_eq!(buffer.document_cursor_position(), Position::new(0, 4));
+        assert_eq!(buffer.cursor_position(), Position::new(0, 2));
+
+        buffer.proccess_command(Command::MoveCursorUp(1)).unwrap();
+        assert_eq!(buffer.document_cursor_position(), Position::new(0, 1));
+    }
+
+    #[test]
+    fn test_toggle_and_delete_fold() {
+        let mut buffer = folded_buffer();
+
+        buffer.proccess_command(Command::ToggleFold).unwrap();
+        assert_eq!(rendered_text(&buffer), vec!["0", "1", "2", "3", "4"]);
+
+        buffer.proccess_command(Command::MoveCursorDown(2)).unwrap();
+        buffer.proccess_command(Command::ToggleFold).unwrap();
+        assert_eq!(buffer.document_cursor_position(), Position::new(0, 1));
+        assert_eq!(rendered_text(&buffer)[1], "+--  3 lines: 1 ----");
+
+        buffer.proccess_command(Command::DeleteFold).unwrap();
+        assert_eq!(rendered_text(&buffer), vec!["0", "1", "2", "3", "4"]);
+    }
+
+    fn fold_summary(buffer: &Buffer) -> Option<String> {
+        rendered_text(buffer)
+            .into_iter()
+            .find(|line| line.starts_with("+--"))
+    }
+
+    #[test]
+    fn test_deleting_rows_above_fold_keeps_cursor_in_document() {
+        let mut buffer = folded_buffer();
+        buffer.proccess_command(Command::MoveCursorUp(1)).unwrap();
+
+        buffer.proccess_command(Command::DeleteLine).unwrap();
+        buffer.proccess_command(Command::DeleteLine).unwrap();
+        assert_eq!(rendered_text(&buffer)[0], "+--  2 lines: 2 ----");
+
+        buffer.proccess_command(Command::MoveCursorDown(1)).unwrap();
+        buffer.proccess_command(Command::MoveCursorDown(1)).unwrap();
+        assert!(buffer.document_cursor_position().y <= buffer.lines_in_document());
+        buffer.proccess_command(Command::InsertChar('x')).unwrap();
+    }
+
+    #[test]
+    fn test_folds_follow_edits_above_and_inside() {
+        let mut buffer = folded_buffer();
+
+        buffer.proccess_command(Command::MoveCursorUp(1)).unwrap();
+        buffer.proccess_command(Command::InsertLineBreak).unwrap();
+        assert_eq!(
+            rendered_text(&buffer)[..3],
+            ["", "0", "+--  3 lines: 1 ----"]
+        );
+
+        buffer.proccess_command(Command::MoveCursorUp(1)).unwrap();
+        buffer.proccess_command(Command::JoinLines).unwrap();
+        assert_eq!(rendered_text(&buffer)[..2], ["0", "+--  3 lines: 1 ----"]);
+
+        buffer.proccess_command(Command::MoveCursorDown(1)).unwrap();
+        buffer.proccess_command(Command::ToggleFold).unwrap();
+        buffer.proccess_command(Command::MoveCursorDown(1)).unwrap();
+        buffer.proccess_command(Command::JoinLines).unwrap();
+        buffer.proccess_command(Command::InsertLineBreak).unwrap();
+        buffer.proccess_command(Command::DeleteLine).unwrap();
+        buffer.proccess_command(Command::MoveCursorUp(1)).unwrap();
+        buffer.proccess_command(Command::ToggleFold).unwrap();
+        assert_eq!(
+            rendered_text(&buffer),
+            vec!["0", "+--  2 lines: 1 ----", "4", "~", "~"]
+        );
+
+        for pattern in &["2", "1"] {
+            buffer
+                .proccess_command(Command::Global(
+                    (*pattern).into(),
+                    Box::new(Command::DeleteLine),
+                ))
+                .unwrap();
+        }
+        assert_eq!(rendered_text(&buffer), vec!["0", "4", "~", "~", "~"]);
+        assert_eq!(fold_summary(&buffer), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_filter_over_fold_removes_it() {
+        let mut buffer = folded_buffer();
+
+        buffer
+            .proccess_command(Command::Filter(LineRange::Lines(1, 1), "cat".into()))
+            .unwrap();
+        assert_eq!(fold_summary(&buffer), Some("+--  3 lines: 1 ----".into()));
+
+        buffer
+            .proccess_command(Command::Filter(LineRange::All, "sort -r".into()))
+            .unwrap();
+        assert_eq!(rendered_text(&buffer), vec!["4", "3", "2", "1", "0"]);
+        assert_eq!(buffer.drain_changes().len(), 2);
+    }
+
+    #[test]
+    fn test_set_file_name_detects_file_type() {
+        let mut buffer = Buffer::new(Document::from("print()"), Rect::new(80, 10));
+
+        buffer
+            .proccess_command(Command::SetFileName("foo.py".into()))
+            .unwrap();
+
+        assert_eq!(buffer.file_type(), FileType::Python);
+        assert_eq!(buffer.document_name(), "foo.py");
+        assert_eq!(buffer.drain_messages(), vec!["\"foo.py\" 1L, 8B"]);
+        assert!(!std::path::Path::new("foo.py").exists());
+    }
+
+    #[test]
+    fn test_set_file_name_moves_swap_file() {
+        let old = env::temp_dir().join("vie_test_file_rename.txt");
+        let new = env::temp_dir().join("vie_test_file_rename.py");
+        let mut buffer = Buffer::new(Document::from("print()"), Rect::new(80, 10));
+        buffer.document.set_file_name(old.to_str().unwrap());
+        buffer.write_swap().unwrap();
+        let old_swap = buffer.document.swap_file_name().unwrap();
+
+        buffer
+            .proccess_command(Command::SetFileName(new.to_str().unwrap().into()))
+            .unwrap();
+
+        let new_swap = buffer.document.swap_file_name().unwrap();
+        assert!(!old_swap.exists());
+        assert_eq!(fs::read_to_string(&new_swap).unwrap(), "print()\n");
+        assert!(!new.exists());
+
+        fs::remove_file(new_swap).unwrap();
+    }
+
+    #[test]
+    fn test_options_are_local_to_buffer() {
+        let mut tabbed = Buffer::new(Document::from("ab"), Rect::new(80, 10));
+        let mut expanded = Buffer::new(Document::from("ab"), Rect::new(80, 10));
+
+        tabbed
+            .proccess_command(Command::Set(Setting::ExpandTab(false)))
+            .unwrap();
+
+        for buffer in [&mut tabbed, &mut expanded] {
+            buffer
+                .proccess_command(Command::MoveCursorRight(1))
+                .unwrap();
+            buffer.proccess_command(Command::InsertTab).unwrap();
+        }
+
+        assert_eq!(tabbed.document.row(0).unwrap().as_str(), "a\tb");
+        assert_eq!(expanded.document.row(0).unwrap().as_str(), "a   b");
+        assert_eq!(expanded.document_cursor_position(), Position::new(4, 0));
+    }
+
+    #[test]
+    fn test_toggle_comment_operator() {
+        let mut buffer = Buffer::new(Document::from("a\nb\nc"), Rect::new(80, 10));
+        buffer
+            .proccess_command(Command::Operate(
+                Operator::ToggleComment,
+                Box::new(Command::MoveCursorDown(1)),
+            ))
+            .unwrap();
+
+        let rows: Vec<&str> = buffer.document.lines().map(Row::as_str).collect();
+        assert_eq!(rows, vec!["// a", "// b", "c"]);
+    }
+
+    #[test]
+    fn test_global_delete() {
+        let contents = "keep\nTODO one\nkeep too\nTODO two";
+
+        let mut buffer = Buffer::new(Document::from(contents), Rect::new(80, 10));
+        buffer
+            .proccess_command(Command::Global(
+                "TODO".into(),
+                Box::new(Command::DeleteLine),
+            ))
+            .unwrap();
+        let rows: Vec<&str> = buffer.document.lines().map(Row::as_str).collect();
+        assert_eq!(rows, vec!["keep", "keep too"]);
+
+        let mut buffer = Buffer::new(Document::from(contents), Rect::new(80, 10));
+        buffer
+            .proccess_command(Command::InvertedGlobal(
+                "TODO".into(),
+                Box::new(Command::DeleteLine),
+            ))
+            .unwrap();
+        let rows: Vec<&str> = buffer.document.lines().map(Row::as_str).collect();
+        assert_eq!(rows, vec!["TODO one", "TODO two"]);
+    }
+
+    #[test]
+    fn test_highlight_trailing_whitespace() {
+        let mut buffer = Buffer::new(Document::from("ab  "), Rect::new(10, 1));
+        buffer
+            .proccess_command(Command::Set(Setting::HighlightTrailingWhitespace(true)))
+            .unwrap();
+
+        let area = Rect::new(10, 1);
+        let mut frame = FrameBuffer::empty(area);
+        buffer.render(&mut frame);
+
+        let highlighted: Vec<usize> = FrameBuffer::empty(area)
+            .diff(&frame)
+            .into_iter()
+            .filter(|cell| *cell.style() == trailing_whitespace_style())
+            .map(|cell| cell.position().x)
+            .collect();
+        assert_eq!(highlighted, vec![2, 3]);
+    }
+
+    fn wrapped_buffer(contents: &str) -> Buffer {
+        let mut buffer = Buffer::new(Document::from(contents), Rect::new(10, 5));
+        buffer
+            .proccess_command(Command::Set(Setting::Wrap(true)))
+            .unwrap();
+        buffer
+    }
+
+    #[test]
+    fn test_display_line_motion_on_wrapped_line() {
+        let mut buffer = wrapped_buffer(&format!("{}\nshort", "x".repeat(25)));
+        buffer
+            .proccess_command(Command::MoveCursorRight(3))
+            .unwrap();
+
+        buffer
+            .proccess_command(Command::MoveCursorDisplayLineDown(1))
+            .unwrap();
+        assert_eq!(buffer.cursor_position, Position::new(13, 0));
+        assert_eq!(buffer.cursor_position(), Position::new(3, 1));
+
+        buffer
+            .proccess_command(Command::MoveCursorDisplayLineDown(2))
+            .unwrap();
+        assert_eq!(buffer.cursor_position, Position::new(3, 1));
+        assert_eq!(buffer.cursor_position(), Position::new(3, 3));
+
+        buffer
+            .proccess_command(Command::MoveCursorDisplayLineUp(1))
+            .unwrap();
+        assert_eq!(buffer.cursor_position, Position::new(23, 0));
+        assert_eq!(buffer.cursor_position(), Position::new(3, 2));
+    }
+
+    #[test]
+    fn test_display_line_motion_on_row_filling_its_last_line() {
+        let mut buffer = wrapped_buffer(&format!("{}\nshort", "x".repeat(20)));
+
+        buffer
+            .proccess_command(Command::MoveCursorDisplayLineDown(1))
+            .unwrap();
+        assert_eq!(buffer.cursor_position, Position::new(10, 0));
+
+        buffer
+            .proccess_command(Command::MoveCursorDisplayLineDown(1))
+            .unwrap();
+        assert_eq!(buffer.cursor_position, Position::new(0, 1));
+
+        buffer
+            .proccess_command(Command::MoveCursorDisplayLineDown(1))
+            .unwrap();
+        assert_eq!(buffer.cursor_position, Position::new(0, 1));
+    }
+
+    #[test]
+    fn test_cursor_at_end_of_row_filling_its_last_line() {
+        let mut buffer = wrapped_buffer(&format!("a\nb\nc\n{}", "x".repeat(20)));
+        buffer.proccess_command(Command::MoveCursorDown(3)).unwrap();
+
+        buffer.proccess_command(Command::MoveCursorLineEnd).unwrap();
+
+        assert_eq!(buffer.document_cursor_position(), Position::new(20, 3));
+        assert_eq!(buffer.cursor_position(), Position::new(9, 4));
+        assert_eq!(buffer.offset.y, 0);
+    }
+
+    #[test]
+    fn test_logical_line_motion_on_wrapped_line() {
+        let mut buffer = wrapped_buffer(&format!("{}\nshort", "x".repeat(25)));
+        buffer
+            .proccess_command(Command::MoveCursorRight(3))
+            .unwrap();
+
+        buffer.proccess_command(Command::MoveCursorDown(1)).unwrap();
+
+        assert_eq!(buffer.cursor_position, Position::new(3, 1));
+        assert_eq!(buffer.cursor_position(), Position::new(3, 3));
+    }
+
+    #[test]
+    fn test_wrapped_scroll_keeps_cursor_in_view() {
+        let mut buffer = wrapped_buffer(&format!("{}\none\ntwo\nthree", "x".repeat(25)));
+
+        buffer.proccess_command(Command::MoveCursorDown(3)).unwrap();
+
+        assert_eq!(buffer.offset.y, 1);
+        assert_eq!(buffer.cursor_position(), Position::new(0, 2));
+    }
+
+    #[test]
+    fn test_display_line_motion_without_wrap() {
+        let mut buffer = Buffer::new(Document::from("one\ntwo"), Rect::new(10, 3));
+
+        buffer
+            .proccess_command(Command::MoveCursorDisplayLineDown(1))
+            .unwrap();
+
+        assert_eq!(buffer.cursor_position, Position::new(0, 1));
+    }
+
+    #[test]
+    fn test_repeat() {
+        let mut buffer = Buffer::new(
+            Document::from("abcdef\n1\n2\n3\n4\n5\n6"),
+            Rect::new(80, 24),
+        );
+
+        buffer
+            .proccess_command(Command::Repeat(3, Box::new(Command::DeleteCharUnderCursor)))
+            .unwrap();
+        assert_eq!(buffer.document.row(0).unwrap().as_str(), "def");
+
+        buffer
+            .proccess_command(Command::Repeat(5, Box::new(Command::MoveCursorDown(1))))
+            .unwrap();
+        assert_eq!(buffer.document_cursor_position(), Position::new(0, 5));
+
+        buffer
+            .proccess_command(Command::Repeat(2, Box::new(Command::DeleteLine)))
+            .unwrap();
+        assert_eq!(buffer.lines_in_document(), 5);
+        assert_eq!(buffer.document.row(4).unwrap().as_str(), "4");
+        assert_eq!(buffer.document_cursor_position(), Position::new(0, 4));
+    }
+
+    #[test]
+    fn test_repeat_does_not_move_past_end_of_document() {
+        let mut buffer = Buffer::new(Document::from("1\n2"), Rect::new(80, 24));
+
+        buffer
+            .proccess_command(Command::Repeat(10, Box::new(Command::MoveCursorDown(1))))
+            .unwrap();
+        assert_eq!(buffer.document_cursor_position(), Position::new(0, 1));
+
+        buffer
+            .proccess_command(Command::MoveCursorPageDown)
+            .unwrap();
+        buffer
+            .proccess_command(Command::MoveCursorRight(1))
+            .unwrap();
+        buffer
+            .proccess_command(Command::MoveCursorRight(1))
+            .unwrap();
+        assert_eq!(buffer.document_cursor_position(), Position::new(1, 1));
+    }
+
+    #[test]
+    fn test_delete_char_under_cursor_stops_at_end_of_row() {
+        let mut buffer = Buffer::new(Document::from("ab\ncd"), Rect::new(80, 24));
+        buffer
+            .proccess_command(Command::MoveCursorRight(1))
+            .unwrap();
+
+        buffer
+            .proccess_command(Command::Repeat(3, Box::new(Command::DeleteCharUnderCursor)))
+            .unwrap();
+
+        let rows: Vec<&str> = buffer.document.lines().map(Row::as_str).collect();
+        assert_eq!(rows, vec!["a", "cd"]);
+    }
+
+    #[test]
+    fn test_reflow_operator_uses_text_width() {
+        let mut buffer = Buffer::new(Document::from("aaa bbb ccc\nddd"), Rect::new(80, 24));
+        buffer
+            .proccess_command(Command::Set(Setting::TextWidth(7)))
+            .unwrap();
+        buffer
+            .proccess_command(Command::Operate(
+                Operator::Reflow,
+                Box::new(Command::MoveCursorDown(1)),
+            ))
+            .unwrap();
+
+        assert_eq!(buffer.lines_in_document(), 2);
+        assert_eq!(buffer.document.row(0).unwrap().as_str(), "aaa bbb");
+        assert_eq!(buffer.document.row(1).unwrap().as_str(), "ccc ddd");
+        assert_eq!(buffer.cursor_position(), Position::new(0, 0));
     }
 }
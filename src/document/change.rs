@@ -0,0 +1,29 @@
+use crate::ui::layout::Position;
+
+/// An edit made to a document: the text from `start` up to `end` was replaced with
+/// `new_text`. Columns count graphemes and every row ends with a line break, the way the
+/// document is saved, which is the shape incremental sync with a language server expects.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Change {
+    pub start: Position,
+    pub end: Position,
+    pub new_text: String,
+}
+
+impl Change {
+    pub fn new(start: Position, end: Position, new_text: &str) -> Self {
+        Self {
+            start,
+            end,
+            new_text: new_text.into(),
+        }
+    }
+
+    pub fn insert(at: Position, text: &str) -> Self {
+        Self::new(at, at, text)
+    }
+
+    pub fn delete(start: Position, end: Position) -> Self {
+        Self::new(start, end, "")
+    }
+}
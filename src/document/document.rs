@@ -1,45 +1,265 @@
-use crate::{document::Row, ui::layout::Position};
-use anyhow::{Context, Error, Result};
+use crate::{
+    document::{Change, Encoding, FileType, LineEnding, Row},
+    ui::layout::Position,
+};
+use anyhow::{Error, Result};
+use std::{
+    io,
+    ops::Range,
+    path::{Path, PathBuf},
+};
+use thiserror::Error as ThisError;
+use unicode_segmentation::UnicodeSegmentation;
+
+#[derive(Debug, ThisError)]
+pub enum OpenError {
+    #[error("\"{0}\" is a directory")]
+    IsADirectory(String),
+    #[error("\"{0}\" is not valid UTF-8")]
+    InvalidUtf8(String),
+    #[error("unable to read \"{0}\": {1}")]
+    Io(String, #[source] io::Error),
+}
+
+#[derive(Debug, ThisError)]
+pub enum SaveError {
+    #[error("the document has no file name")]
+    NoFileName,
+    #[error("permission denied writing \"{0}\"")]
+    PermissionDenied(String),
+    #[error("the directory of \"{0}\" does not exist")]
+    NotFound(String),
+    #[error("line {1} of \"{0}\" can not be written in the file encoding")]
+    ConversionFailed(String, usize),
+    #[error("unable to write \"{0}\": {1}")]
+    Other(String, #[source] io::Error),
+}
+
+impl SaveError {
+    fn from_io(file_name: &str, error: io::Error) -> Self {
+        match error.kind() {
+            io::ErrorKind::PermissionDenied => Self::PermissionDenied(file_name.into()),
+            io::ErrorKind::NotFound => Self::NotFound(file_name.into()),
+            _ => Self::Other(file_name.into(), error),
+        }
+    }
+}
 
 #[derive(Default)]
 pub struct Document {
     file_name: Option<String>,
     rows: Vec<Row>,
+    dirty: bool,
+    encoding: Encoding,
+    line_ending: LineEnding,
+    file_type: FileType,
+    /// The byte offset each row starts at on disk, followed by the offset just past the last
+    /// row, so that a line can be found without measuring the rows before it.
+    line_offsets: Vec<usize>,
+    changes: Vec<Change>,
 }
 
 impl Document {
-    pub fn open(filename: &str) -> Result<Self> {
-        use std::fs;
+    /// Opens the file for editing. A file that does not exist yet opens as an empty document
+    /// that will be created when it is saved.
+    pub fn open(filename: &str) -> Result<Self, OpenError> {
+        Self::open_with_encoding(filename, Encoding::Utf8)
+    }
 
-        let contents = fs::read_to_string(filename).context("unable to read from file")?;
-        let mut rows = Vec::new();
+    /// Opens the file, decoding it with the given encoding. The document is written back in
+    /// the same encoding, and with the same line ending, when saved.
+    pub fn open_with_encoding(filename: &str, encoding: Encoding) -> Result<Self, OpenError> {
+        use std::fs;
 
-        for row in contents.lines() {
-            rows.push(Row::from(row));
+        if fs::metadata(filename).is_ok_and(|metadata| metadata.is_dir()) {
+            return Err(OpenError::IsADirectory(filename.into()));
         }
 
-        Ok(Self {
+        let contents = match fs::read(filename) {
+            Ok(bytes) => encoding
+                .decode(bytes)
+                .ok_or_else(|| OpenError::InvalidUtf8(filename.into()))?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(OpenError::Io(filename.into(), e)),
+        };
+
+        let mut document = Self {
             file_name: Some(String::from(filename)),
-            rows,
-        })
+            file_type: FileType::detect(filename),
+            encoding,
+            line_ending: LineEnding::detect(&contents),
+            ..Self::from(&contents[..])
+        };
+        document.rebuild_line_offsets();
+
+        Ok(document)
+    }
+
+    pub fn save(&mut self) -> Result<(), SaveError> {
+        let file_name = self.file_name.as_ref().ok_or(SaveError::NoFileName)?;
+
+        // Nothing is written unless every line can be, so the file on disk is left whole.
+        let contents = self
+            .encoded()
+            .map_err(|line| SaveError::ConversionFailed(file_name.clone(), line))?;
+        std::fs::write(file_name, contents).map_err(|e| SaveError::from_io(file_name, e))?;
+        self.dirty = false;
+
+        // The file is saved either way, so a swap file that can't be removed is left behind
+        // rather than failing the save.
+        let _ = self.remove_swap();
+
+        Ok(())
+    }
+
+    /// The swap file that unsaved changes are written to, next to the file itself.
+    pub fn swap_file_name(&self) -> Option<PathBuf> {
+        let path = Path::new(self.file_name.as_ref()?);
+        let name = path.file_name()?.to_str()?;
+
+        Some(path.with_file_name(format!(".{name}.swp")))
+    }
+
+    /// Writes the document to its swap file without marking it as saved. A document without
+    /// a name has no swap file.
+    pub fn write_swap(&self) -> Result<(), io::Error> {
+        let Some(path) = self.swap_file_name() else {
+            return Ok(());
+        };
+
+        let contents = self.encoded().map_err(|line| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("conversion failed in line {line}"),
+            )
+        })?;
+
+        std::fs::write(path, contents)
+    }
+
+    /// Removes the swap file, once its changes have been saved or discarded. A swap file that
+    /// was never written is not an error.
+    pub fn remove_swap(&self) -> Result<(), io::Error> {
+        match self.swap_file_name().map(std::fs::remove_file) {
+            Some(Err(e)) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+
+    /// The contents of the document as they are written to disk, or the number of the first
+    /// line that the encoding can not represent.
+    fn encoded(&self) -> Result<Vec<u8>, usize> {
+        let mut contents = Vec::with_capacity(self.byte_len());
+
+        for (index, row) in self.lines().enumerate() {
+            let line = self.encoding.encode(row.as_str()).ok_or(index + 1)?;
+            contents.extend(line);
+            contents.extend(self.line_ending.as_bytes());
+        }
+
+        Ok(contents)
+    }
+
+    pub fn save_as(&mut self, filename: &str) -> Result<(), SaveError> {
+        self.set_file_name(filename);
+        self.save()
+    }
+
+    /// The number of bytes the document takes up on disk once saved, including the line
+    /// ending written after every row.
+    pub fn byte_len(&self) -> usize {
+        self.line_offset(self.len()).unwrap_or(0)
+    }
+
+    /// The byte offset the row starts at once saved. The offset one past the last row is the
+    /// length of the document.
+    pub fn line_offset(&self, index: usize) -> Option<usize> {
+        self.line_offsets.get(index).copied()
     }
 
-    pub fn save(&mut self, filename: Option<&str>) -> Result<(), std::io::Error> {
-        use {std::fs::File, std::io::Write};
+    /// Takes the changes made to the document since the last call, in the order they were
+    /// made.
+    pub fn drain_changes(&mut self) -> Vec<Change> {
+        std::mem::take(&mut self.changes)
+    }
+
+    /// Records an edit that has already been made to the rows, marking the document as
+    /// modified.
+    fn changed(&mut self, change: Change) {
+        self.dirty = true;
+        self.update_line_offsets(&change);
+        self.changes.push(change);
+    }
+
+    /// Records that the rows from `start` up to `end` were replaced with the rows from
+    /// `start` up to `new_end`.
+    fn rows_replaced(&mut self, start: usize, end: usize, new_end: usize) {
+        let mut new_text = String::new();
+        for row in &self.rows[start..new_end] {
+            new_text.push_str(row.as_str());
+            new_text.push('\n');
+        }
 
-        if let Some(filename) = filename {
-            self.file_name = Some(filename.into());
+        self.changed(Change::new(
+            Position::new(0, start),
+            Position::new(0, end),
+            &new_text,
+        ));
+    }
+
+    /// Calculates the line offsets of every row from scratch.
+    fn rebuild_line_offsets(&mut self) {
+        self.line_offsets.clear();
+        self.update_line_offsets(&Change::insert(Position::default(), ""));
+    }
+
+    /// Updates the line offsets after the change has been made to the rows. Only the rows
+    /// the change touched are measured again; the rows after them have only moved, so their
+    /// offsets are shifted by the difference in length.
+    fn update_line_offsets(&mut self, change: &Change) {
+        if self.line_offsets.is_empty() {
+            self.line_offsets.push(0);
         }
 
-        if let Some(file_name) = &self.file_name {
-            let mut file = File::create(file_name)?;
-            for row in &self.rows {
-                file.write_all(row.as_bytes())?;
-                file.write_all(b"\n")?;
+        let old_len = self.line_offsets.len() - 1;
+        let start = change.start.y.min(old_len);
+        let old_end = (change.end.y + 1).min(old_len).max(start);
+        let new_end = self.rows.len() - (old_len - old_end);
+
+        let line_ending_len = self.line_ending.as_bytes().len();
+        let mut offset = self.line_offsets[start];
+        let measured: Vec<usize> = self.rows[start..new_end]
+            .iter()
+            .map(|row| {
+                offset += self.encoding.encoded_len(row.as_str()) + line_ending_len;
+                offset
+            })
+            .collect();
+
+        let old_offset = self.line_offsets[old_end];
+        if offset != old_offset {
+            for later in &mut self.line_offsets[old_end + 1..] {
+                *later = *later - old_offset + offset;
             }
         }
 
-        Ok(())
+        self.line_offsets.splice(start + 1..=old_end, measured);
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    pub fn encoding(&self) -> Encoding {
+        self.encoding
+    }
+
+    pub fn set_encoding(&mut self, encoding: Encoding) {
+        if self.encoding != encoding {
+            self.encoding = encoding;
+            self.dirty = true;
+            self.rebuild_line_offsets();
+        }
     }
 
     pub fn delete(&mut self, at: &Position) {
@@ -47,52 +267,338 @@ impl Document {
             return;
         }
 
-        if at.x == self.rows.get_mut(at.y).unwrap().len() && at.y < self.len() - 1 {
-            let next_row = self.rows.remove(at.y + 1);
-            let row = self.rows.get_mut(at.y).unwrap();
-            row.append(&next_row);
+        if at.x >= self.rows[at.y].len() {
+            self.merge_with_next(at.y);
             return;
         }
 
-        let row = self.rows.get_mut(at.y).unwrap();
-        row.delete(at.x);
+        self.rows[at.y].delete(at.x);
+        self.changed(Change::delete(*at, Position::new(at.x + 1, at.y)));
+    }
+
+    /// Removes the row at `index`, returning false when there is no such row.
+    pub fn delete_row(&mut self, index: usize) -> bool {
+        if index >= self.len() {
+            return false;
+        }
+
+        self.rows.remove(index);
+        self.changed(Change::delete(
+            Position::new(0, index),
+            Position::new(0, index + 1),
+        ));
+
+        true
+    }
+
+    /// Deletes the grapheme before the position, joining the row onto the end of the previous
+    /// row when the position is at the start of a line. Returns where the cursor should be
+    /// placed after the deletion.
+    pub fn delete_backward(&mut self, at: &Position) -> Position {
+        if at.x > 0 {
+            if let Some(row) = self.rows.get_mut(at.y).filter(|row| at.x <= row.len()) {
+                row.delete(at.x - 1);
+                self.changed(Change::delete(Position::new(at.x - 1, at.y), *at));
+            }
+
+            return Position::new(at.x - 1, at.y);
+        }
+
+        if at.y == 0 || at.y > self.len() {
+            return *at;
+        }
+
+        let previous_len = self.rows[at.y - 1].len();
+        self.merge_with_next(at.y - 1);
+
+        Position::new(previous_len, at.y - 1)
+    }
+
+    /// Appends the row after `index` onto the end of it, returning false when there is no
+    /// following row to merge.
+    pub fn merge_with_next(&mut self, index: usize) -> bool {
+        if index + 1 >= self.len() {
+            return false;
+        }
+
+        let end_of_row = Position::new(self.rows[index].len(), index);
+        let next_row = self.rows.remove(index + 1);
+        self.rows[index].append(&next_row);
+        self.changed(Change::delete(end_of_row, Position::new(0, index + 1)));
+
+        true
     }
 
-    pub fn insert(&mut self, at: &Position, ch: char) -> Result<()> {
+    /// Joins the row after `index` onto it the way `J` does, replacing the next row's leading
+    /// whitespace with a single space. Returns the position of the join point.
+    pub fn join_lines(&mut self, index: usize) -> Option<Position> {
+        if index + 1 >= self.len() {
+            return None;
+        }
+
+        let next = self.rows[index + 1].as_str().trim_start().to_string();
+        let current = &self.rows[index];
+        let separator = if current.len() == 0 || next.is_empty() || current.as_str().ends_with(' ')
+        {
+            ""
+        } else {
+            " "
+        };
+
+        let join_point = Position::new(current.len(), index);
+        self.rows[index + 1] = Row::from(&format!("{separator}{next}")[..]);
+        self.rows_replaced(index + 1, index + 2, index + 2);
+        self.merge_with_next(index);
+
+        Some(join_point)
+    }
+
+    /// Inserts the character at the position, returning the position just after it.
+    pub fn insert(&mut self, at: &Position, ch: char) -> Result<Position> {
         use std::cmp::Ordering;
 
         match at.y.cmp(&self.len()) {
             Ordering::Equal => {
                 let mut row = Row::default();
-                row.insert(0, ch);
+                let x = row.insert(0, ch);
                 self.rows.push(row);
+                self.changed(Change::insert(Position::new(0, at.y), &format!("{ch}\n")));
 
-                Ok(())
+                Ok(Position::new(x, at.y))
             }
             Ordering::Less => {
                 let row = self.rows.get_mut(at.y).unwrap();
-                row.insert(at.x, ch);
-                Ok(())
+                let start = Position::new(at.x.min(row.len()), at.y);
+                let x = row.insert(at.x, ch);
+                self.changed(Change::insert(start, &ch.to_string()));
+
+                Ok(Position::new(x, at.y))
             }
-            Ordering::Greater => Err(Error::from(std::io::Error::new(
-                std::io::ErrorKind::Other,
+            Ordering::Greater => Err(Error::from(io::Error::other(
                 "trying to insert character past current string length",
             ))),
         }
     }
 
-    pub fn insert_newline(&mut self, at: &Position) {
+    /// Splits the row at the position, moving the rest of it onto a new row below. The empty
+    /// line just past the end of the document is created first, so breaking it gives two
+    /// rows like breaking any other line. Returns the start of the new row.
+    pub fn insert_newline(&mut self, at: &Position) -> Option<Position> {
         if at.y > self.len() {
-            return;
+            return None;
         }
 
         if at.y == self.len() {
             self.rows.push(Row::default());
-            return;
+            self.changed(Change::insert(Position::new(0, at.y), "\n"));
         }
 
-        let new_row = self.rows.get_mut(at.y).unwrap().split(at.x);
+        let start = Position::new(at.x.min(self.rows[at.y].len()), at.y);
+        let new_row = self.rows[at.y].split(at.x);
         self.rows.insert(at.y + 1, new_row);
+        self.changed(Change::insert(start, "\n"));
+
+        Some(Position::new(0, at.y + 1))
+    }
+
+    /// Adds `width` spaces of indentation to the start of the row, returning the number of
+    /// graphemes inserted.
+    pub fn indent_row(&mut self, index: usize, width: usize) -> usize {
+        if let Some(row) = self.rows.get_mut(index) {
+            *row = Row::from(&format!("{}{}", " ".repeat(width), row.as_str())[..]);
+            self.changed(Change::insert(Position::new(0, index), &" ".repeat(width)));
+            return width;
+        }
+
+        0
+    }
+
+    /// Adds `width` columns of indentation to the row the way `noexpandtab` does, rewriting
+    /// its leading whitespace as tabs of `tab_stop` columns, plus spaces for any remainder.
+    /// Returns how many graphemes of leading whitespace the row had before and after.
+    pub fn indent_row_with_tabs(
+        &mut self,
+        index: usize,
+        width: usize,
+        tab_stop: usize,
+    ) -> Option<(usize, usize)> {
+        let tab_stop = tab_stop.max(1);
+        let row = self.rows.get_mut(index)?;
+        let line = row.as_str();
+        let rest = line.trim_start_matches([' ', '\t']);
+        let before = line.len() - rest.len();
+
+        let columns = line[..before].chars().fold(0, |columns, ch| {
+            if ch == '\t' {
+                columns / tab_stop * tab_stop + tab_stop
+            } else {
+                columns + 1
+            }
+        }) + width;
+        let indent = format!(
+            "{}{}",
+            "\t".repeat(columns / tab_stop),
+            " ".repeat(columns % tab_stop)
+        );
+        let after = indent.len();
+
+        *row = Row::from(&format!("{indent}{rest}")[..]);
+        self.changed(Change::new(
+            Position::new(0, index),
+            Position::new(before, index),
+            &indent,
+        ));
+
+        Some((before, after))
+    }
+
+    /// Removes up to `width` columns of leading whitespace from the row, returning the number
+    /// of graphemes removed. A tab counts as a full `width` of indentation.
+    pub fn dedent_row(&mut self, index: usize, width: usize) -> usize {
+        if let Some(row) = self.rows.get_mut(index) {
+            let mut columns = 0;
+            let removed = row
+                .as_str()
+                .chars()
+                .take_while(|&ch| {
+                    let fits = columns < width && (ch == ' ' || ch == '\t');
+                    columns += if ch == '\t' { width } else { 1 };
+                    fits
+                })
+                .count();
+
+            if removed > 0 {
+                *row = Row::from(&row.as_str()[removed..]);
+                self.changed(Change::delete(
+                    Position::new(0, index),
+                    Position::new(removed, index),
+                ));
+            }
+
+            return removed;
+        }
+
+        0
+    }
+
+    /// Rewraps the paragraphs in the given rows so that no line is longer than `text_width`,
+    /// keeping blank lines between paragraphs and the indentation of each paragraph's first
+    /// line. Returns the number of rows the range occupies after reflowing.
+    pub fn reflow_range(&mut self, range: Range<usize>, text_width: usize) -> usize {
+        let end = range.end.min(self.len());
+        let start = range.start.min(end);
+
+        let mut reflowed = Vec::new();
+        let mut paragraph: Vec<&str> = Vec::new();
+        let mut indent = "";
+
+        for row in &self.rows[start..end] {
+            let line = row.as_str();
+
+            if line.trim().is_empty() {
+                reflow_paragraph(&mut reflowed, indent, &paragraph, text_width);
+                paragraph.clear();
+                reflowed.push(Row::default());
+                continue;
+            }
+
+            if paragraph.is_empty() {
+                indent = &line[..line.len() - line.trim_start().len()];
+            }
+
+            paragraph.extend(line.split_whitespace());
+        }
+
+        reflow_paragraph(&mut reflowed, indent, &paragraph, text_width);
+
+        let len = reflowed.len();
+        self.rows.splice(start..end, reflowed);
+        self.rows_replaced(start, end, start + len);
+
+        len
+    }
+
+    /// Replaces the rows in the range with the lines of `contents`, returning how many rows
+    /// they became.
+    pub fn replace_rows(&mut self, range: Range<usize>, contents: &str) -> usize {
+        let end = range.end.min(self.len());
+        let start = range.start.min(end);
+
+        let rows: Vec<Row> = contents.lines().map(Row::from).collect();
+        let len = rows.len();
+        self.rows.splice(start..end, rows);
+        self.rows_replaced(start, end, start + len);
+
+        len
+    }
+
+    /// Comments out every non-blank row in the range by adding the token after the row's
+    /// indentation, unless they are all commented already, in which case the token is
+    /// removed from each of them instead. Returns whether the rows were commented.
+    pub fn toggle_comment_range(&mut self, range: Range<usize>, token: &str) -> bool {
+        let end = range.end.min(self.len());
+        let start = range.start.min(end);
+
+        let comment = self.rows[start..end].iter().any(|row| {
+            let line = row.as_str().trim_start();
+            !line.is_empty() && !line.starts_with(token)
+        });
+
+        let mut toggled_any = false;
+        for row in &mut self.rows[start..end] {
+            let line = row.as_str();
+            let rest = line.trim_start();
+
+            if rest.is_empty() {
+                continue;
+            }
+
+            let indent = &line[..line.len() - rest.len()];
+            let toggled = if comment {
+                format!("{indent}{token} {rest}")
+            } else {
+                let rest = &rest[token.len()..];
+                format!("{indent}{}", rest.strip_prefix(' ').unwrap_or(rest))
+            };
+
+            *row = Row::from(&toggled[..]);
+            toggled_any = true;
+        }
+
+        if toggled_any {
+            self.rows_replaced(start, end, end);
+        }
+
+        comment
+    }
+
+    /// The token that starts a line comment in the document.
+    pub fn comment_token(&self) -> &'static str {
+        self.file_type.comment_token()
+    }
+
+    /// Renames the document without saving it, detecting its file type again from the new
+    /// name. A swap file already written moves to the new name with it. Setting the name it
+    /// already has changes nothing.
+    pub fn set_file_name(&mut self, file_name: &str) {
+        if self.file_name.as_deref() == Some(file_name) {
+            return;
+        }
+
+        let previous_swap = self.swap_file_name();
+
+        self.file_name = Some(file_name.into());
+        self.file_type = FileType::detect(file_name);
+
+        // There is no swap to move when none was written, and one that can't be moved is
+        // written again under the new name on the next pause in typing.
+        if let (Some(from), Some(to)) = (previous_swap, self.swap_file_name()) {
+            let _ = std::fs::rename(from, to);
+        }
+    }
+
+    pub fn file_type(&self) -> FileType {
+        self.file_type
     }
 
     pub fn file_name(&self) -> Option<&String> {
@@ -103,7 +609,644 @@ impl Document {
         self.rows.get(index)
     }
 
+    pub fn lines(&self) -> impl Iterator<Item = &Row> {
+        self.rows.iter()
+    }
+
+    /// Iterates over the rows in the range. Any part of the range past the end of the document
+    /// is ignored.
+    pub fn lines_in(&self, range: Range<usize>) -> impl Iterator<Item = &Row> {
+        let end = range.end.min(self.rows.len());
+        let start = range.start.min(end);
+
+        self.rows[start..end].iter()
+    }
+
+    /// The indexes of every row that contains the pattern, or with `inverted` of every row
+    /// that does not.
+    pub fn matching_rows(&self, pattern: &str, inverted: bool) -> Vec<usize> {
+        self.lines()
+            .enumerate()
+            .filter(|(_, row)| row.as_str().contains(pattern) != inverted)
+            .map(|(index, _)| index)
+            .collect()
+    }
+
     pub fn len(&self) -> usize {
         self.rows.len()
     }
 }
+
+fn reflow_paragraph(rows: &mut Vec<Row>, indent: &str, words: &[&str], text_width: usize) {
+    let mut line = String::new();
+    let mut line_width = 0;
+
+    for word in words {
+        let word_width = word.graphemes(true).count();
+
+        if line_width > 0 && line_width + 1 + word_width > text_width {
+            rows.push(Row::from(&line[..]));
+            line.clear();
+            line_width = 0;
+        }
+
+        if line_width == 0 {
+            line.push_str(indent);
+            line_width = indent.graphemes(true).count();
+        } else {
+            line.push(' ');
+            line_width += 1;
+        }
+
+        line.push_str(word);
+        line_width += word_width;
+    }
+
+    if !line.is_empty() {
+        rows.push(Row::from(&line[..]));
+    }
+}
+
+impl From<&str> for Document {
+    fn from(contents: &str) -> Self {
+        let mut document = Self {
+            rows: contents.lines().map(Row::from).collect(),
+            ..Self::default()
+        };
+        document.rebuild_line_offsets();
+
+        document
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, fs};
+
+    fn contents(document: &Document) -> Vec<&str> {
+        document.rows.iter().map(Row::as_str).collect()
+    }
+
+    #[test]
+    fn test_lines_in_clamps_to_document() {
+        let document = Document::from("a\nb\nc");
+        let lines = |range| {
+            document
+                .lines_in(range)
+                .map(Row::as_str)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(lines(0..2), vec!["a", "b"]);
+        assert_eq!(lines(1..10), vec!["b", "c"]);
+        assert_eq!(lines(3..5), Vec::<&str>::new());
+        assert_eq!(document.lines().count(), 3);
+    }
+
+    #[test]
+    fn test_save_to_missing_directory() {
+        let path = env::temp_dir().join("vie_test_missing_directory/file.txt");
+        let path = path.to_str().unwrap();
+
+        let result = Document::from("text").save_as(path);
+
+        assert!(matches!(result, Err(SaveError::NotFound(name)) if name == path));
+    }
+
+    #[test]
+    fn test_write_swap() {
+        let path = env::temp_dir().join("vie_test_swap.txt");
+        let swap = env::temp_dir().join(".vie_test_swap.txt.swp");
+        let _ = fs::remove_file(&swap);
+
+        let mut document = Document::from("unsaved");
+        document.file_name = Some(path.to_str().unwrap().into());
+        document.dirty = true;
+
+        assert_eq!(document.swap_file_name(), Some(swap.clone()));
+        document.write_swap().unwrap();
+
+        assert_eq!(fs::read_to_string(&swap).unwrap(), "unsaved\n");
+        assert!(document.is_dirty());
+        assert!(!path.exists());
+
+        fs::remove_file(swap).unwrap();
+    }
+
+    #[test]
+    fn test_save_removes_swap_file() {
+        let path = env::temp_dir().join("vie_test_save_swap.txt");
+        let swap = env::temp_dir().join(".vie_test_save_swap.txt.swp");
+        let renamed = env::temp_dir().join("vie_test_save_swap_renamed.txt");
+
+        let mut document = Document::from("unsaved");
+        document.file_name = Some(path.to_str().unwrap().into());
+        document.write_swap().unwrap();
+        assert!(swap.exists());
+
+        document.save().unwrap();
+        assert!(!swap.exists());
+
+        document.write_swap().unwrap();
+        document.save_as(renamed.to_str().unwrap()).unwrap();
+        assert!(!swap.exists());
+        assert!(!document.swap_file_name().unwrap().exists());
+
+        fs::remove_file(path).unwrap();
+        fs::remove_file(renamed).unwrap();
+    }
+
+    #[test]
+    fn test_save_refuses_characters_the_encoding_can_not_represent() {
+        let path = env::temp_dir().join("vie_test_save_conversion.txt");
+        let path = path.to_str().unwrap();
+        let _ = fs::remove_file(path);
+
+        let mut document = Document::from("café\n日本");
+        document.set_file_name(path);
+        document.set_encoding(Encoding::Latin1);
+
+        assert!(matches!(
+            document.save(),
+            Err(SaveError::ConversionFailed(name, 2)) if name == path
+        ));
+        assert!(document.is_dirty());
+        assert!(!Path::new(path).exists());
+    }
+
+    #[test]
+    fn test_save_without_file_name() {
+        let mut document = Document::from("text");
+        document.insert(&Position::new(0, 0), '!').unwrap();
+
+        assert!(matches!(document.save(), Err(SaveError::NoFileName)));
+        assert!(document.is_dirty());
+    }
+
+    #[test]
+    fn test_save_error_from_io_error_kind() {
+        let error = |kind| SaveError::from_io("file.txt", io::Error::from(kind));
+
+        assert!(matches!(
+            error(io::ErrorKind::PermissionDenied),
+            SaveError::PermissionDenied(name) if name == "file.txt"
+        ));
+        assert!(matches!(
+            error(io::ErrorKind::NotFound),
+            SaveError::NotFound(_)
+        ));
+        assert!(matches!(
+            error(io::ErrorKind::WriteZero),
+            SaveError::Other(_, _)
+        ));
+    }
+
+    #[test]
+    fn test_byte_len_matches_saved_file() {
+        let tests = vec![
+            ("vie_test_byte_len_lf.txt", "one\ntwo\n", LineEnding::Lf),
+            (
+                "vie_test_byte_len_crlf.txt",
+                "one\r\ntwo\r\n",
+                LineEnding::Crlf,
+            ),
+        ];
+
+        for (name, contents, line_ending) in tests {
+            let path = env::temp_dir().join(name);
+            let path = path.to_str().unwrap();
+            fs::write(path, contents).unwrap();
+
+            let mut document = Document::open(path).unwrap();
+            assert_eq!(document.line_ending, line_ending);
+            assert_eq!(document.byte_len(), contents.len());
+
+            document.save().unwrap();
+            assert_eq!(fs::read_to_string(path).unwrap(), contents);
+
+            fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_open_directory() {
+        let path = env::temp_dir();
+        let path = path.to_str().unwrap();
+
+        assert!(matches!(
+            Document::open(path),
+            Err(OpenError::IsADirectory(name)) if name == path
+        ));
+    }
+
+    #[test]
+    fn test_open_invalid_utf8() {
+        let path = env::temp_dir().join("vie_test_open_invalid_utf8.txt");
+        fs::write(&path, [b'o', b'k', 0xff, 0xfe, b'\n']).unwrap();
+        let path = path.to_str().unwrap();
+
+        let result = Document::open(path);
+        fs::remove_file(path).unwrap();
+
+        assert!(matches!(result, Err(OpenError::InvalidUtf8(name)) if name == path));
+    }
+
+    #[test]
+    fn test_open_and_save_latin1() {
+        let path = env::temp_dir().join("vie_test_open_latin1.txt");
+        let bytes = vec![b'c', b'a', b'f', 0xe9, b'\n', 0xfc, b'\n'];
+        fs::write(&path, &bytes).unwrap();
+        let path = path.to_str().unwrap();
+
+        assert!(Document::open(path).is_err());
+
+        let mut document = Document::open_with_encoding(path, Encoding::Latin1).unwrap();
+        assert_eq!(document.encoding(), Encoding::Latin1);
+        assert_eq!(contents(&document), vec!["café", "ü"]);
+
+        document.save().unwrap();
+        let saved = fs::read(path).unwrap();
+        fs::remove_file(path).unwrap();
+
+        assert_eq!(saved, bytes);
+    }
+
+    #[test]
+    fn test_open_missing_file() {
+        let path = env::temp_dir().join("vie_test_open_missing_file.txt");
+        let _ = fs::remove_file(&path);
+        let path = path.to_str().unwrap();
+
+        let document = Document::open(path).unwrap();
+
+        assert_eq!(document.file_name(), Some(&path.to_string()));
+        assert_eq!(document.len(), 0);
+    }
+
+    #[test]
+    fn test_reflow_range_wraps_on_whitespace() {
+        let text = "The quick brown fox jumps over the lazy dog and keeps on running \
+                    through the field until the sun goes down behind the hills.";
+        let mut document = Document::from(text);
+
+        let len = document.reflow_range(0..1, 40);
+
+        assert_eq!(len, document.len());
+        assert!(len > 1);
+        for line in contents(&document) {
+            assert!(line.len() <= 40, "{:?} is longer than 40", line);
+            assert_eq!(line, line.trim());
+        }
+        assert_eq!(
+            contents(&document).join(" ").split(' ').collect::<Vec<_>>(),
+            text.split_whitespace().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_reflow_range_keeps_paragraphs_and_indentation() {
+        let mut document = Document::from("    one two\n    three four\n\nfive six\nuntouched");
+
+        assert_eq!(document.reflow_range(0..4, 14), 4);
+
+        assert_eq!(
+            contents(&document),
+            vec!["    one two", "    three four", "", "five six", "untouched"]
+        );
+
+        assert_eq!(document.reflow_range(0..2, 79), 1);
+        assert_eq!(contents(&document)[0], "    one two three four");
+    }
+
+    #[test]
+    fn test_delete_backward_within_row() {
+        let mut document = Document::from("abc");
+
+        assert_eq!(
+            document.delete_backward(&Position::new(2, 0)),
+            Position::new(1, 0)
+        );
+        assert_eq!(contents(&document), vec!["ac"]);
+        assert_eq!(
+            document.delete_backward(&Position::new(0, 0)),
+            Position::new(0, 0)
+        );
+        assert_eq!(contents(&document), vec!["ac"]);
+    }
+
+    #[test]
+    fn test_delete_backward_joins_at_line_start() {
+        let mut document = Document::from("one\ntwo\nthree");
+
+        assert_eq!(
+            document.delete_backward(&Position::new(0, 2)),
+            Position::new(3, 1)
+        );
+        assert_eq!(contents(&document), vec!["one", "twothree"]);
+
+        assert_eq!(
+            document.delete_backward(&Position::new(0, 2)),
+            Position::new(8, 1)
+        );
+        assert_eq!(contents(&document), vec!["one", "twothree"]);
+    }
+
+    #[test]
+    fn test_insert_returns_position_after_character() {
+        let mut document = Document::from("ac");
+
+        assert_eq!(
+            document.insert(&Position::new(1, 0), 'b').unwrap(),
+            Position::new(2, 0)
+        );
+        assert_eq!(
+            document.insert(&Position::new(3, 0), '日').unwrap(),
+            Position::new(4, 0)
+        );
+        assert_eq!(contents(&document), vec!["abc日"]);
+    }
+
+    #[test]
+    fn test_insert_combining_character_joins_previous_grapheme() {
+        let mut document = Document::from("e");
+
+        assert_eq!(
+            document.insert(&Position::new(1, 0), '\u{301}').unwrap(),
+            Position::new(1, 0)
+        );
+        assert_eq!(document.row(0).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_toggle_comment_range() {
+        let mut document = Document::from("fn main() {\n\n    // done\n    run();\n}");
+
+        assert!(document.toggle_comment_range(1..4, "//"));
+        assert_eq!(
+            contents(&document),
+            vec!["fn main() {", "", "    // // done", "    // run();", "}"]
+        );
+
+        assert!(!document.toggle_comment_range(1..4, "//"));
+        assert_eq!(
+            contents(&document),
+            vec!["fn main() {", "", "    // done", "    run();", "}"]
+        );
+
+        assert!(!document.toggle_comment_range(2..3, "//"));
+        assert_eq!(contents(&document)[2], "    done");
+    }
+
+    #[test]
+    fn test_comment_token_from_extension() {
+        let mut document = Document::default();
+        assert_eq!(document.comment_token(), "//");
+
+        document.set_file_name("script.py");
+        assert_eq!(document.comment_token(), "#");
+    }
+
+    #[test]
+    fn test_delete_row() {
+        let mut document = Document::from("one\ntwo");
+
+        assert!(document.delete_row(0));
+        assert_eq!(contents(&document), vec!["two"]);
+        assert!(!document.delete_row(1));
+    }
+
+    #[test]
+    fn test_merge_with_next() {
+        let mut document = Document::from("one\ntwo\nthree");
+
+        assert!(document.merge_with_next(1));
+        assert_eq!(contents(&document), vec!["one", "twothree"]);
+
+        assert!(!document.merge_with_next(1));
+        assert!(!document.merge_with_next(5));
+        assert_eq!(contents(&document), vec!["one", "twothree"]);
+    }
+
+    #[test]
+    fn test_delete_at_line_end_merges_with_next() {
+        let mut document = Document::from("one\ntwo");
+
+        document.delete(&Position::new(3, 0));
+        assert_eq!(contents(&document), vec!["onetwo"]);
+
+        document.delete(&Position::new(6, 0));
+        assert_eq!(contents(&document), vec!["onetwo"]);
+    }
+
+    #[test]
+    fn test_join_lines() {
+        let mut document = Document::from("one\n    two\n\nthree");
+
+        assert_eq!(document.join_lines(0), Some(Position::new(3, 0)));
+        assert_eq!(contents(&document), vec!["one two", "", "three"]);
+
+        assert_eq!(document.join_lines(1), Some(Position::new(0, 1)));
+        assert_eq!(contents(&document), vec!["one two", "three"]);
+
+        assert_eq!(document.join_lines(1), None);
+    }
+
+    #[test]
+    fn test_indent_and_dedent_row() {
+        let mut document = Document::from("  two\n\ttab");
+
+        assert_eq!(document.indent_row(0, 4), 4);
+        assert_eq!(contents(&document)[0], "      two");
+
+        assert_eq!(document.dedent_row(0, 4), 4);
+        assert_eq!(document.dedent_row(0, 4), 2);
+        assert_eq!(document.dedent_row(0, 4), 0);
+        assert_eq!(contents(&document)[0], "two");
+
+        assert_eq!(document.dedent_row(1, 4), 1);
+        assert_eq!(contents(&document)[1], "tab");
+
+        assert_eq!(document.indent_row(5, 4), 0);
+    }
+
+    #[test]
+    fn test_indent_row_with_tabs() {
+        let mut document = Document::from("  two\n\tthree");
+
+        assert_eq!(document.indent_row_with_tabs(0, 4, 8), Some((2, 6)));
+        assert_eq!(contents(&document)[0], "      two");
+        assert_eq!(document.indent_row_with_tabs(0, 4, 8), Some((6, 3)));
+        assert_eq!(contents(&document)[0], "\t  two");
+
+        assert_eq!(document.indent_row_with_tabs(1, 8, 8), Some((1, 2)));
+        assert_eq!(contents(&document)[1], "\t\tthree");
+
+        assert_eq!(document.indent_row_with_tabs(2, 4, 8), None);
+    }
+
+    fn line_offsets(document: &Document) -> Vec<usize> {
+        (0..=document.len())
+            .map(|index| document.line_offset(index).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_line_offsets_follow_edits_in_the_middle() {
+        let mut document = Document::from("one\ntwo\nthree\nfour");
+        assert_eq!(line_offsets(&document), vec![0, 4, 8, 14, 19]);
+
+        document.insert(&Position::new(1, 1), 'x').unwrap();
+        assert_eq!(line_offsets(&document), vec![0, 4, 9, 15, 20]);
+
+        document.insert_newline(&Position::new(2, 1));
+        assert_eq!(
+            contents(&document),
+            vec!["one", "tx", "wo", "three", "four"]
+        );
+        assert_eq!(line_offsets(&document), vec![0, 4, 7, 10, 16, 21]);
+
+        document.delete(&Position::new(2, 1));
+        assert_eq!(line_offsets(&document), vec![0, 4, 9, 15, 20]);
+
+        document.delete_row(2);
+        assert_eq!(line_offsets(&document), vec![0, 4, 9, 14]);
+        assert_eq!(document.line_offset(5), None);
+        assert_eq!(document.byte_len(), 14);
+    }
+
+    #[test]
+    fn test_line_offsets_follow_rows_added_and_removed() {
+        let mut document = Document::from("one\ntwo\nthree");
+        let rebuilt =
+            |document: &Document| line_offsets(&Document::from(&contents(document).join("\n")[..]));
+
+        document.replace_rows(1..2, "a\nbb\nccc");
+        assert_eq!(line_offsets(&document), rebuilt(&document));
+
+        document.join_lines(0);
+        assert_eq!(line_offsets(&document), rebuilt(&document));
+
+        document
+            .insert(&Position::new(0, document.len()), 'z')
+            .unwrap();
+        assert_eq!(line_offsets(&document), rebuilt(&document));
+
+        document.delete_row(document.len() - 1);
+        document.delete_row(0);
+        assert_eq!(line_offsets(&document), vec![0, 3, 7, 13]);
+        assert_eq!(line_offsets(&document), rebuilt(&document));
+    }
+
+    #[test]
+    fn test_line_offsets_count_encoded_bytes() {
+        let mut document = Document::from("café\nx");
+        assert_eq!(line_offsets(&document), vec![0, 6, 8]);
+
+        document.set_encoding(Encoding::Latin1);
+        assert_eq!(line_offsets(&document), vec![0, 5, 7]);
+    }
+
+    #[test]
+    fn test_character_edits_produce_changes() {
+        let mut document = Document::from("ab\ncd");
+        let at = |x, y| Position::new(x, y);
+
+        document.insert(&at(1, 0), 'x').unwrap();
+        assert_eq!(
+            document.drain_changes(),
+            vec![Change::insert(at(1, 0), "x")]
+        );
+
+        document.delete(&at(0, 1));
+        assert_eq!(
+            document.drain_changes(),
+            vec![Change::delete(at(0, 1), at(1, 1))]
+        );
+
+        document.delete_backward(&at(3, 0));
+        assert_eq!(
+            document.drain_changes(),
+            vec![Change::delete(at(2, 0), at(3, 0))]
+        );
+
+        document.insert(&at(0, 2), 'z').unwrap();
+        assert_eq!(
+            document.drain_changes(),
+            vec![Change::insert(at(0, 2), "z\n")]
+        );
+        assert_eq!(contents(&document), vec!["ax", "d", "z"]);
+        assert!(document.drain_changes().is_empty());
+    }
+
+    #[test]
+    fn test_line_edits_produce_changes() {
+        let mut document = Document::from("one\n  two\nthree");
+        let at = |x, y| Position::new(x, y);
+
+        document.insert_newline(&at(1, 0));
+        assert_eq!(
+            document.drain_changes(),
+            vec![Change::insert(at(1, 0), "\n")]
+        );
+
+        document.delete_backward(&at(0, 1));
+        assert_eq!(
+            document.drain_changes(),
+            vec![Change::delete(at(1, 0), at(0, 1))]
+        );
+
+        document.join_lines(0);
+        assert_eq!(
+            document.drain_changes(),
+            vec![
+                Change::new(at(0, 1), at(0, 2), " two\n"),
+                Change::delete(at(3, 0), at(0, 1)),
+            ]
+        );
+
+        document.delete_row(1);
+        assert_eq!(
+            document.drain_changes(),
+            vec![Change::delete(at(0, 1), at(0, 2))]
+        );
+
+        document.insert_newline(&at(0, 1));
+        assert_eq!(
+            document.drain_changes(),
+            vec![
+                Change::insert(at(0, 1), "\n"),
+                Change::insert(at(0, 1), "\n"),
+            ]
+        );
+        assert_eq!(contents(&document), vec!["one two", "", ""]);
+    }
+
+    #[test]
+    fn test_row_rewrites_produce_changes() {
+        let mut document = Document::from("a\nb\nc");
+        let at = |x, y| Position::new(x, y);
+
+        document.indent_row(1, 2);
+        document.dedent_row(1, 1);
+        assert_eq!(
+            document.drain_changes(),
+            vec![
+                Change::insert(at(0, 1), "  "),
+                Change::delete(at(0, 1), at(1, 1)),
+            ]
+        );
+
+        document.toggle_comment_range(0..2, "#");
+        assert_eq!(
+            document.drain_changes(),
+            vec![Change::new(at(0, 0), at(0, 2), "# a\n # b\n")]
+        );
+
+        document.reflow_range(0..3, 80);
+        assert_eq!(
+            document.drain_changes(),
+            vec![Change::new(at(0, 0), at(0, 3), "# a # b c\n")]
+        );
+    }
+}
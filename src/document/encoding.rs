@@ -0,0 +1,70 @@
+use std::{
+    convert::TryFrom,
+    fmt::{self, Display, Formatter},
+};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum Encoding {
+    #[default]
+    Utf8,
+    Latin1,
+}
+
+impl Encoding {
+    /// Decodes the bytes, returning `None` when they are not valid in this encoding.
+    pub fn decode(self, bytes: Vec<u8>) -> Option<String> {
+        match self {
+            Self::Utf8 => String::from_utf8(bytes).ok(),
+            Self::Latin1 => Some(bytes.into_iter().map(char::from).collect()),
+        }
+    }
+
+    /// Encodes the string, returning `None` when it has a character the encoding can not
+    /// represent.
+    pub fn encode(self, string: &str) -> Option<Vec<u8>> {
+        match self {
+            Self::Utf8 => Some(string.as_bytes().to_vec()),
+            Self::Latin1 => string.chars().map(|ch| u8::try_from(ch).ok()).collect(),
+        }
+    }
+
+    /// The number of bytes the string takes up once encoded.
+    pub fn encoded_len(self, string: &str) -> usize {
+        match self {
+            Self::Utf8 => string.len(),
+            Self::Latin1 => string.chars().count(),
+        }
+    }
+}
+
+impl Display for Encoding {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Utf8 => write!(f, "utf-8"),
+            Self::Latin1 => write!(f, "latin1"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_latin1_round_trip() {
+        let bytes = vec![b'c', b'a', b'f', 0xe9];
+
+        let decoded = Encoding::Latin1.decode(bytes.clone()).unwrap();
+
+        assert_eq!(decoded, "café");
+        assert_eq!(Encoding::Latin1.encode(&decoded), Some(bytes.clone()));
+        assert_eq!(Encoding::Latin1.encode("a日"), None);
+        assert_eq!(Encoding::Latin1.encoded_len(&decoded), bytes.len());
+    }
+
+    #[test]
+    fn test_utf8_rejects_invalid_bytes() {
+        assert_eq!(Encoding::Utf8.decode(vec![0xe9]), None);
+        assert_eq!(Encoding::Utf8.decode("café".into()), Some("café".into()));
+    }
+}
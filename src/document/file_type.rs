@@ -0,0 +1,83 @@
+use std::{
+    fmt::{self, Display, Formatter},
+    path::Path,
+};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum FileType {
+    #[default]
+    Text,
+    Rust,
+    Python,
+    Shell,
+    Ruby,
+    Toml,
+    Yaml,
+    Lua,
+    Sql,
+    Haskell,
+}
+
+impl FileType {
+    /// Detects the file type from the extension of the file name.
+    pub fn detect(file_name: &str) -> Self {
+        let extension = Path::new(file_name)
+            .extension()
+            .and_then(|extension| extension.to_str());
+
+        match extension {
+            Some("rs") => Self::Rust,
+            Some("py") => Self::Python,
+            Some("sh" | "bash") => Self::Shell,
+            Some("rb") => Self::Ruby,
+            Some("toml") => Self::Toml,
+            Some("yaml" | "yml") => Self::Yaml,
+            Some("lua") => Self::Lua,
+            Some("sql") => Self::Sql,
+            Some("hs") => Self::Haskell,
+            _ => Self::Text,
+        }
+    }
+
+    /// The token that starts a line comment. Plain text has no comment syntax of its own and
+    /// uses `//`.
+    pub fn comment_token(self) -> &'static str {
+        match self {
+            Self::Python | Self::Shell | Self::Ruby | Self::Toml | Self::Yaml => "#",
+            Self::Lua | Self::Sql | Self::Haskell => "--",
+            Self::Text | Self::Rust => "//",
+        }
+    }
+}
+
+impl Display for FileType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Text => "text",
+            Self::Rust => "rust",
+            Self::Python => "python",
+            Self::Shell => "sh",
+            Self::Ruby => "ruby",
+            Self::Toml => "toml",
+            Self::Yaml => "yaml",
+            Self::Lua => "lua",
+            Self::Sql => "sql",
+            Self::Haskell => "haskell",
+        };
+
+        write!(f, "{name}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(FileType::detect("src/main.rs"), FileType::Rust);
+        assert_eq!(FileType::detect("script.py"), FileType::Python);
+        assert_eq!(FileType::detect("Makefile"), FileType::Text);
+        assert_eq!(FileType::detect("notes.txt").comment_token(), "//");
+    }
+}
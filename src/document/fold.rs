@@ -0,0 +1,205 @@
+use crate::{
+    document::Change,
+    ui::layout::{Position, Span},
+};
+
+/// A manually created fold over the rows from `start` up to `end`. A closed fold is displayed
+/// as a single summary line.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Fold {
+    pub start: usize,
+    pub end: usize,
+    pub closed: bool,
+}
+
+impl Fold {
+    pub fn contains(&self, y: usize) -> bool {
+        (self.start..self.end).contains(&y)
+    }
+
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Folds {
+    folds: Vec<Fold>,
+}
+
+impl Folds {
+    /// Adds a closed fold over the rows. Folds may be nested but are expected not to overlap
+    /// partially.
+    pub fn create(&mut self, start: usize, end: usize) {
+        self.folds.push(Fold {
+            start,
+            end,
+            closed: true,
+        });
+    }
+
+    /// Opens or closes the innermost fold containing the row, returning it after toggling.
+    pub fn toggle(&mut self, y: usize) -> Option<Fold> {
+        let index = self.innermost(y)?;
+        let fold = &mut self.folds[index];
+        fold.closed = !fold.closed;
+
+        Some(*fold)
+    }
+
+    /// Removes the innermost fold containing the row, leaving the rows themselves untouched.
+    pub fn delete(&mut self, y: usize) -> bool {
+        match self.innermost(y) {
+            Some(index) => {
+                self.folds.remove(index);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// The outermost closed fold containing the row, which hides every row in it but the first.
+    pub fn closed_at(&self, y: usize) -> Option<&Fold> {
+        self.folds
+            .iter()
+            .filter(|fold| fold.closed && fold.contains(y))
+            .max_by_key(|fold| fold.len())
+    }
+
+    /// Moves the folds to follow the rows the change inserted or removed. A fold shrinks to
+    /// the rows of it that are left, and is removed when none are.
+    pub fn apply(&mut self, change: &Change) {
+        let Change { start, end, .. } = *change;
+        let added = change.new_text.matches('\n').count();
+
+        // A row is gone when the change replaced it from its first column up to the next
+        // row. The last row lives on with whatever follows the change.
+        let removed = Span::new(start, Position::new(0, end.y));
+        let kept = |y: usize| !removed.contains(&Position::new(0, y));
+        let moved = |y: usize| {
+            if y >= end.y {
+                y - end.y + start.y + added
+            } else {
+                y
+            }
+        };
+        let last_kept_before_end = if start.x > 0 {
+            Some(start.y)
+        } else {
+            start.y.checked_sub(1)
+        };
+
+        self.folds.retain_mut(|fold| {
+            let last = fold.end - 1;
+            let first_kept = if kept(fold.start) {
+                Some(fold.start)
+            } else {
+                Some(end.y).filter(|&y| y <= last)
+            };
+            let last_kept = if kept(last) {
+                Some(last)
+            } else {
+                last_kept_before_end.filter(|&y| y >= fold.start)
+            };
+
+            match (first_kept, last_kept) {
+                (Some(first), Some(last)) if first <= last => {
+                    fold.start = moved(first);
+                    fold.end = moved(last) + 1;
+                    true
+                }
+                _ => false,
+            }
+        });
+    }
+
+    fn innermost(&self, y: usize) -> Option<usize> {
+        self.folds
+            .iter()
+            .enumerate()
+            .filter(|(_, fold)| fold.contains(y))
+            .min_by_key(|(_, fold)| fold.len())
+            .map(|(index, _)| index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nested_folds() {
+        let mut folds = Folds::default();
+        folds.create(2, 4);
+        folds.create(0, 6);
+
+        assert_eq!(folds.closed_at(3).map(|fold| fold.start), Some(0));
+
+        assert_eq!(folds.toggle(3).map(|fold| fold.closed), Some(false));
+        assert_eq!(folds.closed_at(3).map(|fold| fold.start), Some(0));
+
+        assert!(folds.delete(1));
+        assert_eq!(folds.closed_at(3), None);
+        assert!(!folds.delete(5));
+    }
+
+    fn folds_after(change: &Change) -> Vec<(usize, usize)> {
+        let mut folds = Folds::default();
+        folds.create(2, 5);
+        folds.apply(change);
+
+        folds
+            .folds
+            .iter()
+            .map(|fold| (fold.start, fold.end))
+            .collect()
+    }
+
+    #[test]
+    fn test_apply_moves_folds_with_rows() {
+        let at = Position::new;
+
+        // A row deleted above, inside, and at either end of the fold.
+        assert_eq!(
+            folds_after(&Change::delete(at(0, 0), at(0, 1))),
+            vec![(1, 4)]
+        );
+        assert_eq!(
+            folds_after(&Change::delete(at(0, 3), at(0, 4))),
+            vec![(2, 4)]
+        );
+        assert_eq!(
+            folds_after(&Change::delete(at(0, 2), at(0, 3))),
+            vec![(2, 4)]
+        );
+        assert_eq!(
+            folds_after(&Change::delete(at(0, 4), at(0, 5))),
+            vec![(2, 4)]
+        );
+
+        // Lines joined, and broken, above and inside the fold.
+        assert_eq!(
+            folds_after(&Change::delete(at(1, 0), at(0, 1))),
+            vec![(1, 4)]
+        );
+        assert_eq!(
+            folds_after(&Change::delete(at(1, 3), at(0, 4))),
+            vec![(2, 4)]
+        );
+        assert_eq!(folds_after(&Change::insert(at(1, 0), "\n")), vec![(3, 6)]);
+        assert_eq!(folds_after(&Change::insert(at(1, 3), "\n")), vec![(2, 6)]);
+
+        // Edits within a row or below the fold leave it alone.
+        assert_eq!(folds_after(&Change::insert(at(0, 3), "x")), vec![(2, 5)]);
+        assert_eq!(
+            folds_after(&Change::delete(at(0, 5), at(0, 6))),
+            vec![(2, 5)]
+        );
+
+        // Rows replaced around the fold remove it.
+        assert_eq!(
+            folds_after(&Change::new(at(0, 1), at(0, 6), "a\nb\n")),
+            vec![]
+        );
+    }
+}
@@ -0,0 +1,35 @@
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum LineEnding {
+    #[default]
+    Lf,
+    Crlf,
+}
+
+impl LineEnding {
+    /// The line ending used by the first line of the contents.
+    pub fn detect(contents: &str) -> Self {
+        match contents.find('\n') {
+            Some(index) if contents[..index].ends_with('\r') => Self::Crlf,
+            _ => Self::Lf,
+        }
+    }
+
+    pub fn as_bytes(self) -> &'static [u8] {
+        match self {
+            Self::Lf => b"\n",
+            Self::Crlf => b"\r\n",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(LineEnding::detect("a\r\nb\r\n"), LineEnding::Crlf);
+        assert_eq!(LineEnding::detect("a\nb\r\n"), LineEnding::Lf);
+        assert_eq!(LineEnding::detect("no line ending"), LineEnding::Lf);
+    }
+}
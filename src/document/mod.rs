@@ -1,7 +1,19 @@
 mod buffer;
+mod change;
+#[allow(clippy::module_inception)]
 mod document;
+mod encoding;
+mod file_type;
+mod fold;
+mod line_ending;
+mod options;
 mod row;
 
 pub use buffer::Buffer;
-pub use document::Document;
+pub use change::Change;
+pub use document::{Document, OpenError, SaveError};
+pub use encoding::Encoding;
+pub use file_type::FileType;
+pub use line_ending::LineEnding;
+pub use options::Options;
 pub use row::Row;
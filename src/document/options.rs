@@ -0,0 +1,51 @@
+use crate::ops::Setting;
+
+const DEFAULT_TEXT_WIDTH: usize = 79;
+const DEFAULT_SHIFT_WIDTH: usize = 4;
+const DEFAULT_TAB_STOP: usize = 4;
+const DEFAULT_END_OF_BUFFER_CHAR: char = '~';
+
+/// The options that can be set for each buffer with `:set`. The editor keeps a global copy,
+/// changed with `:setglobal`, that new buffers start from.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Options {
+    pub text_width: usize,
+    pub shift_width: usize,
+    pub tab_stop: usize,
+    pub expand_tab: bool,
+    pub end_of_buffer_char: char,
+    pub wrap: bool,
+    pub highlight_trailing_whitespace: bool,
+}
+
+impl Options {
+    /// Applies the setting, ignoring settings that are not buffer options.
+    pub fn apply(&mut self, setting: Setting) {
+        match setting {
+            Setting::TextWidth(width) => self.text_width = width,
+            Setting::ShiftWidth(width) => self.shift_width = width,
+            Setting::TabStop(width) => self.tab_stop = width,
+            Setting::ExpandTab(expand_tab) => self.expand_tab = expand_tab,
+            Setting::EndOfBufferChar(ch) => self.end_of_buffer_char = ch,
+            Setting::Wrap(wrap) => self.wrap = wrap,
+            Setting::HighlightTrailingWhitespace(highlight) => {
+                self.highlight_trailing_whitespace = highlight;
+            }
+            Setting::FileEncoding(_) | Setting::UpdateTime(_) => {}
+        }
+    }
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            text_width: DEFAULT_TEXT_WIDTH,
+            shift_width: DEFAULT_SHIFT_WIDTH,
+            tab_stop: DEFAULT_TAB_STOP,
+            expand_tab: true,
+            end_of_buffer_char: DEFAULT_END_OF_BUFFER_CHAR,
+            wrap: false,
+            highlight_trailing_whitespace: false,
+        }
+    }
+}
@@ -1,36 +1,95 @@
+use std::cell::Cell;
 use unicode_segmentation::UnicodeSegmentation;
 
 #[derive(Debug, Default)]
 pub struct Row {
     string: String,
     len: usize,
+    /// The display width last measured, along with the tab width it was measured for.
+    /// Cleared whenever the row changes.
+    width: Cell<Option<(usize, usize)>>,
 }
 
 impl Row {
-    pub fn to_string(&self, start: usize, end: usize) -> String {
-        use std::cmp;
-
-        let end = cmp::min(end, self.string.len());
-        let start = cmp::min(start, end);
+    /// Renders the columns from `start` up to `end` of the row, expanding tabs to the next
+    /// multiple of `tab_width`. Every other grapheme occupies a single column.
+    pub fn to_string(&self, start: usize, end: usize, tab_width: usize) -> String {
         let mut result = String::new();
+        let mut column = 0;
+
+        for grapheme in self.string[..].graphemes(true) {
+            if column >= end {
+                break;
+            }
 
-        for grapheme in self.string[..]
-            .graphemes(true)
-            .skip(start)
-            .take(end - start)
-        {
             if grapheme == "\t" {
-                result.push_str(" ");
+                let next_column = next_tab_stop(column, tab_width);
+                let visible = next_column.min(end).saturating_sub(column.max(start));
+                result.push_str(&" ".repeat(visible));
+                column = next_column;
             } else {
-                result.push_str(grapheme);
+                if column >= start {
+                    result.push_str(grapheme);
+                }
+
+                column += 1;
             }
         }
 
         result
     }
 
+    /// The display column at which the grapheme at index `at` starts.
+    pub fn display_column(&self, at: usize, tab_width: usize) -> usize {
+        self.string[..]
+            .graphemes(true)
+            .take(at)
+            .fold(0, |column, grapheme| {
+                if grapheme == "\t" {
+                    next_tab_stop(column, tab_width)
+                } else {
+                    column + 1
+                }
+            })
+    }
+
+    /// The index of the grapheme displayed at the column, or the length of the row when the
+    /// column is past its end.
+    pub fn grapheme_at_column(&self, column: usize, tab_width: usize) -> usize {
+        let mut current = 0;
+
+        for (index, grapheme) in self.string[..].graphemes(true).enumerate() {
+            current = if grapheme == "\t" {
+                next_tab_stop(current, tab_width)
+            } else {
+                current + 1
+            };
+
+            if current > column {
+                return index;
+            }
+        }
+
+        self.len()
+    }
+
+    /// The number of columns the row occupies when displayed. The width is only measured
+    /// again after the row changes or for a different tab width.
+    pub fn display_width(&self, tab_width: usize) -> usize {
+        if let Some((cached_tab_width, width)) = self.width.get() {
+            if cached_tab_width == tab_width {
+                return width;
+            }
+        }
+
+        let width = self.display_column(self.len(), tab_width);
+        self.width.set(Some((tab_width, width)));
+
+        width
+    }
+
     pub fn contents(&self) -> String {
-        self.to_string(0, self.len())
+        self.string.clone()
     }
 
     pub fn append(&mut self, new: &Self) {
@@ -52,21 +111,24 @@ impl Row {
         self.update_len();
     }
 
-    pub fn insert(&mut self, at: usize, ch: char) {
-        if at >= self.len() {
-            self.string.push(ch);
-            self.update_len();
-            return;
-        }
-
+    /// Inserts the character before the grapheme at index `at`, returning the index just past
+    /// the grapheme the character ended up in. A combining character joins the grapheme
+    /// before it rather than starting a new one.
+    pub fn insert(&mut self, at: usize, ch: char) -> usize {
         let mut result: String = self.string[..].graphemes(true).take(at).collect();
         let remainder: String = self.string[..].graphemes(true).skip(at).collect();
 
         result.push(ch);
+        let inserted_end = result.len();
         result.push_str(&remainder);
         self.string = result;
 
         self.update_len();
+
+        self.string[..]
+            .grapheme_indices(true)
+            .take_while(|(index, _)| *index < inserted_end)
+            .count()
     }
 
     pub fn split(&mut self, at: usize) -> Self {
@@ -82,22 +144,108 @@ impl Row {
     }
 
     fn update_len(&mut self) {
-        self.len = self.string[..].graphemes(true).count()
+        self.len = self.string[..].graphemes(true).count();
+        self.width.set(None);
     }
 
-    pub fn as_bytes(&self) -> &[u8] {
-        self.string.as_bytes()
+    pub fn as_str(&self) -> &str {
+        &self.string
     }
 }
 
+fn next_tab_stop(column: usize, tab_width: usize) -> usize {
+    let tab_width = tab_width.max(1);
+    (column / tab_width + 1) * tab_width
+}
+
 impl From<&str> for Row {
     fn from(slice: &str) -> Self {
         let mut row = Self {
             string: String::from(slice),
-            len: 0,
+            ..Self::default()
         };
 
         row.update_len();
         row
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_string_ascii() {
+        let row = Row::from("hello world");
+
+        assert_eq!(row.to_string(0, 5, 4), "hello");
+        assert_eq!(row.to_string(6, 80, 4), "world");
+        assert_eq!(row.to_string(20, 30, 4), "");
+    }
+
+    #[test]
+    fn test_to_string_expands_tabs_to_tab_stops() {
+        let row = Row::from("a\tb\tc");
+
+        assert_eq!(row.to_string(0, 80, 4), "a   b   c");
+        assert_eq!(row.to_string(2, 6, 4), "  b ");
+        assert_eq!(row.to_string(0, 80, 8), "a       b       c");
+    }
+
+    #[test]
+    fn test_to_string_multibyte() {
+        let row = Row::from("日本語のテキスト");
+
+        assert_eq!(row.to_string(0, 3, 4), "日本語");
+        assert_eq!(row.to_string(3, 5, 4), "のテ");
+        assert_eq!(row.to_string(5, 80, 4), "キスト");
+    }
+
+    #[test]
+    fn test_display_column() {
+        let row = Row::from("\tab\tc");
+
+        assert_eq!(row.display_column(0, 4), 0);
+        assert_eq!(row.display_column(1, 4), 4);
+        assert_eq!(row.display_column(3, 4), 6);
+        assert_eq!(row.display_column(4, 4), 8);
+        assert_eq!(row.display_width(4), 9);
+    }
+
+    #[test]
+    fn test_display_width_follows_edits() {
+        let mut row = Row::from("ab");
+        let measured = |row: &Row, tab_width| row.display_column(row.len(), tab_width);
+
+        assert_eq!(row.display_width(4), 2);
+
+        row.insert(1, '\t');
+        assert_eq!(row.display_width(4), measured(&row, 4));
+        assert_eq!(row.display_width(4), 5);
+        assert_eq!(row.display_width(8), 9);
+
+        row.delete(0);
+        assert_eq!(row.display_width(8), measured(&row, 8));
+        assert_eq!(row.display_width(8), 9);
+
+        row.delete(0);
+        row.append(&Row::from("\tc"));
+        assert_eq!(row.display_width(8), measured(&row, 8));
+        assert_eq!(row.display_width(8), 9);
+
+        let rest = row.split(1);
+        assert_eq!(row.display_width(8), 1);
+        assert_eq!(rest.display_width(8), 9);
+    }
+
+    #[test]
+    fn test_grapheme_at_column() {
+        let row = Row::from("\tab");
+
+        assert_eq!(row.grapheme_at_column(0, 4), 0);
+        assert_eq!(row.grapheme_at_column(3, 4), 0);
+        assert_eq!(row.grapheme_at_column(4, 4), 1);
+        assert_eq!(row.grapheme_at_column(5, 4), 2);
+        assert_eq!(row.grapheme_at_column(9, 4), 3);
+    }
+}
@@ -1,14 +1,17 @@
 use crate::{
+    autosave::{Autosave, SystemClock},
     command_line::CommandLine,
-    document::{Buffer, Document},
+    document::{Buffer, Document, Encoding, OpenError, Options, SaveError},
     io::{
-        event::{CrosstermEventLoop, Event, Loop as EventLoop},
-        CrosstermBackend,
+        event::{CrosstermEventLoop, Event, Key, Loop as EventLoop},
+        Backend, CrosstermBackend,
     },
-    ops::{buffer::Parser as BufferCommandParser, Command},
+    ops::{buffer::Parser as BufferCommandParser, Command, Setting},
     status_bar::StatusBar,
     terminal::Terminal,
     ui::layout::Rect,
+    welcome,
+    window_manager::WindowManager,
 };
 use anyhow::{Context, Result};
 use std::{
@@ -18,19 +21,14 @@ use std::{
     time::Duration,
 };
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
 pub enum Mode {
+    #[default]
     Normal,
     Insert,
     Command,
 }
 
-impl Default for Mode {
-    fn default() -> Self {
-        Mode::Normal
-    }
-}
-
 impl Display for Mode {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match self {
@@ -41,32 +39,57 @@ impl Display for Mode {
     }
 }
 
-pub struct Editor {
-    terminal: Terminal<CrosstermBackend<Stdout>>,
+pub struct Editor<B: Backend = CrosstermBackend<Stdout>> {
+    terminal: Terminal<B>,
     event_loop: Box<dyn EventLoop>,
     should_quit: bool,
-    buffers: Vec<Buffer>,
-    active_buffer_idx: usize,
+    windows: WindowManager,
     mode: Mode,
     buffer_commands: BufferCommandParser,
     status_bar: StatusBar,
     command_line: CommandLine,
+    welcome_screen: Option<welcome::Screen>,
+    autosave: Autosave<SystemClock>,
+    needs_redraw: bool,
+    global_options: Options,
 }
 
 impl Editor {
     pub fn new() -> Result<Self> {
         let args: Vec<String> = env::args().collect();
 
-        let document = if args.len() > 1 {
-            let file_name = &args[1];
-            Document::open(&file_name).unwrap_or_default()
+        let show_welcome_screen = args.len() < 2;
+        let (document, message) = if args.len() > 1 {
+            open_document(&args[1])
         } else {
-            Document::default()
+            (Document::default(), None)
         };
 
         let backend = CrosstermBackend::new(io::stdout());
         let event_loop = Box::new(CrosstermEventLoop::new(Duration::from_millis(250)));
 
+        let mut editor = Self::with_backend(backend, event_loop, document)?;
+
+        if !show_welcome_screen {
+            editor.welcome_screen = None;
+        }
+
+        if let Some(message) = message {
+            editor.command_line.set_message(&message);
+        }
+
+        Ok(editor)
+    }
+}
+
+impl<B: Backend> Editor<B> {
+    /// An editor drawing to the backend and reading from the event loop, with the document
+    /// open in its only window.
+    pub fn with_backend(
+        backend: B,
+        event_loop: Box<dyn EventLoop>,
+        document: Document,
+    ) -> Result<Self> {
         let terminal = Terminal::new(backend).context("unable to create Terminal")?;
 
         let document_viewport =
@@ -90,12 +113,15 @@ impl Editor {
             terminal,
             event_loop,
             should_quit: false,
-            buffers: vec![Buffer::new(document, document_viewport)],
-            active_buffer_idx: 0,
+            windows: WindowManager::new(document_viewport, document),
             mode: Mode::default(),
             buffer_commands: BufferCommandParser::default(),
             status_bar,
             command_line,
+            welcome_screen: Some(welcome::Screen::new(document_viewport)),
+            autosave: Autosave::new(SystemClock),
+            needs_redraw: true,
+            global_options: Options::default(),
         })
     }
 
@@ -103,69 +129,105 @@ impl Editor {
         self.event_loop.start();
 
         loop {
-            self.refresh_screen().context("unable to refresh screen")?;
+            if self.needs_redraw {
+                self.refresh_screen().context("unable to refresh screen")?;
+                self.needs_redraw = false;
+            }
 
             if self.should_quit {
+                for buffer in self.windows.buffers() {
+                    buffer.remove_swap().context("unable to remove swap file")?;
+                }
                 break;
             }
 
             match self.event_loop.next()? {
-                Event::Input(key) => match self.mode {
-                    Mode::Normal | Mode::Insert => {
-                        if let Some(command) =
-                            self.buffer_commands.matched_command_for(key, self.mode)
-                        {
-                            self.process_command(command)
-                                .context("unable to process command")?;
-
-                            self.update_status_bar();
-                        };
+                Event::Input(key) => {
+                    self.autosave.input();
+                    self.needs_redraw = true;
+                    self.process_key(key)?;
+                }
+                // Ticks only redraw when the background work has something to show.
+                Event::Tick => {
+                    let dirty = self.windows.buffers().any(Buffer::is_dirty);
+
+                    if self.autosave.tick(dirty) {
+                        // Every modified buffer is written, even after one of them fails.
+                        let failed = self
+                            .windows
+                            .buffers()
+                            .filter(|buffer| buffer.is_dirty())
+                            .filter_map(|buffer| buffer.write_swap().err())
+                            .last();
+
+                        if let Some(e) = failed {
+                            self.command_line
+                                .set_message(&format!("E303: Unable to write swap file: {e}"));
+                            self.needs_redraw = true;
+                        }
                     }
-                    Mode::Command => {
-                        if let Some(command) = self.command_line.matched_command_for(key) {
-                            self.process_command(command)
-                                .context("unable to process command")?;
+                }
+                Event::Error(e) => return Err(e),
+            }
+        }
 
-                            self.process_command(Command::EnterMode(Mode::Normal))
-                                .context("unable to process command")?;
+        Ok(())
+    }
 
-                            self.update_status_bar();
-                        };
-                    }
-                },
-                Event::Tick => {}
-                Event::Error(e) => return Err(e),
-            };
+    fn process_key(&mut self, key: Key) -> Result<()> {
+        match self.mode {
+            Mode::Normal | Mode::Insert => {
+                if let Some(command) = self.buffer_commands.matched_command_for(key, self.mode) {
+                    self.process_command(command)
+                        .context("unable to process command")?;
+
+                    self.update_status_bar();
+                }
+            }
+            Mode::Command => {
+                if let Some(command) = self.command_line.matched_command_for(key) {
+                    // Return to normal mode first so that any message reported by the
+                    // command is not cleared from the command line.
+                    self.process_command(Command::EnterMode(Mode::Normal))
+                        .context("unable to process command")?;
+
+                    self.process_command(command)
+                        .context("unable to process command")?;
+
+                    self.update_status_bar();
+                }
+            }
         }
 
         Ok(())
     }
 
     fn update_status_bar(&mut self) {
-        let active_buffer = &self.buffers[self.active_buffer_idx];
-
-        self.status_bar.update(
-            self.mode,
-            active_buffer.lines_in_document(),
-            active_buffer.cursor_position(),
-            &active_buffer.document_name(),
-        );
+        self.status_bar.update(self.mode, self.windows.focused());
     }
 
     fn process_command(&mut self, command: Command) -> Result<()> {
-        let actrive_buffer = &mut self.buffers[self.active_buffer_idx];
+        if !matches!(command, Command::EnterMode(Mode::Normal | Mode::Command)) {
+            self.welcome_screen = None;
+        }
 
         if let Command::EnterMode(mode) = command {
+            if mode == Mode::Insert {
+                self.windows.focused_mut().start_insert();
+            } else if self.mode == Mode::Insert {
+                self.windows.focused_mut().finish_insert();
+            }
+
             match mode {
                 Mode::Command => {
                     self.command_line.start_prompt();
                 }
                 Mode::Insert => {
                     self.command_line.clear();
-                    self.command_line.set_message(&format!("-- {} --", mode));
+                    self.command_line.set_message(&format!("-- {mode} --"));
                 }
                 Mode::Normal => self.command_line.clear(),
-            };
+            }
 
             self.mode = mode;
 
@@ -173,11 +235,78 @@ impl Editor {
         }
 
         match command {
+            Command::Repeat(n, command) => {
+                for _ in 0..n {
+                    self.process_command((*command).clone())?;
+                }
+            }
             Command::Quit => self.should_quit = true,
-            _ => actrive_buffer
-                .proccess_command(command)
-                .context("unable to process command on active buffer")?,
-        };
+            Command::SaveIfModifiedAndQuit => {
+                self.process_buffer_command(command)?;
+                // Stay open when the write failed so the changes are not lost.
+                self.should_quit = !self.windows.focused().is_dirty();
+            }
+            // `updatetime` is an editor option, so it is the same however it is set.
+            Command::Set(Setting::UpdateTime(update_time))
+            | Command::SetGlobal(Setting::UpdateTime(update_time)) => {
+                self.autosave.set_update_time(update_time);
+            }
+            // A document's encoding comes from its file, so there is no default to change.
+            Command::SetGlobal(Setting::FileEncoding(_)) => {
+                self.command_line.set_message("E474: Invalid argument");
+            }
+            Command::SetGlobal(setting) => self.global_options.apply(setting),
+            Command::Split(file_name) => {
+                let (document, message) =
+                    file_name.map_or_else(|| (Document::default(), None), |f| open_document(&f));
+
+                if let Some(message) = message {
+                    self.command_line.set_message(&message);
+                }
+
+                self.windows.split(document, self.global_options);
+            }
+            Command::FocusNextWindow
+            | Command::FocusWindowBelow
+            | Command::FocusWindowAbove
+            | Command::CloseWindow
+            | Command::CloseOtherWindows
+            | Command::IncreaseWindowHeight(_)
+            | Command::DecreaseWindowHeight(_) => {
+                self.windows.process_command(&command);
+
+                if let Some(message) = self.windows.drain_messages().pop() {
+                    self.command_line.set_message(&message);
+                }
+            }
+            _ => self.process_buffer_command(command)?,
+        }
+
+        Ok(())
+    }
+
+    /// Passes the command to the focused buffer, showing any message it reports. A failed
+    /// save is reported to the user rather than treated as an error.
+    fn process_buffer_command(&mut self, command: Command) -> Result<()> {
+        let active_buffer = self.windows.focused_mut();
+
+        if let Err(e) = active_buffer.proccess_command(command) {
+            match e.downcast_ref::<SaveError>() {
+                Some(save_error) => {
+                    self.command_line
+                        .set_message(&save_error_message(save_error));
+                }
+                None => return Err(e.context("unable to process command on active buffer")),
+            }
+        }
+
+        if let Some(message) = active_buffer.drain_messages().pop() {
+            self.command_line.set_message(&message);
+        }
+
+        // Nothing subscribes to document changes yet, so they are dropped rather than being
+        // left to pile up.
+        active_buffer.drain_changes();
 
         Ok(())
     }
@@ -189,23 +318,210 @@ impl Editor {
             return Ok(());
         }
 
-        let active_buffer = &self.buffers[self.active_buffer_idx];
+        let windows = &self.windows;
+        let welcome_screen = &self.welcome_screen;
         let status_bar = &self.status_bar;
         let command_line = &self.command_line;
         let mode = &self.mode;
 
         self.terminal.draw(|view| {
-            view.render(active_buffer);
+            view.render(windows);
+            if let Some(welcome_screen) = welcome_screen {
+                view.render(welcome_screen);
+            }
             view.render(status_bar);
             view.render(command_line);
 
             if let Mode::Command = mode {
                 view.set_cursor_position(command_line.cursor_position());
             } else {
-                view.set_cursor_position(active_buffer.cursor_position());
+                view.set_cursor_position(windows.focused().cursor_position());
             }
 
             Ok(())
         })
     }
 }
+
+/// The message shown to the user when the document can not be written, worded like vim's.
+fn save_error_message(error: &SaveError) -> String {
+    match error {
+        SaveError::NoFileName => "E32: No file name".into(),
+        SaveError::PermissionDenied(file_name) => {
+            format!("\"{file_name}\" E212: Can't open file for writing: Permission denied")
+        }
+        SaveError::NotFound(file_name) => {
+            format!("\"{file_name}\" E212: Can't open file for writing: No such file or directory")
+        }
+        SaveError::ConversionFailed(file_name, line) => {
+            format!("\"{file_name}\" E513: Write error, conversion failed in line {line}")
+        }
+        SaveError::Other(file_name, e) => format!("\"{file_name}\" E514: Write error: {e}"),
+    }
+}
+
+/// Opens the document, falling back to an empty document and a message to show the user when
+/// it can not be opened.
+fn open_document(file_name: &str) -> (Document, Option<String>) {
+    match Document::open(file_name) {
+        Ok(document) => (document, None),
+        Err(OpenError::InvalidUtf8(_)) => {
+            match Document::open_with_encoding(file_name, Encoding::Latin1) {
+                Ok(document) => (
+                    document,
+                    Some(format!(
+                        "\"{file_name}\" is not valid UTF-8, opened as latin1"
+                    )),
+                ),
+                Err(e) => (Document::default(), Some(e.to_string())),
+            }
+        }
+        Err(e) => (Document::default(), Some(e.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::mock::{Backend as MockBackend, Loop as MockLoop};
+    use std::fs;
+
+    /// Runs an editor on the document, typing the keys, until it quits or runs out of keys. A
+    /// NUL stands for a tick of the event loop rather than a key.
+    fn run_editor(document: Document, keys: &str) -> Editor<MockBackend> {
+        let events = keys
+            .chars()
+            .map(|ch| match ch {
+                '\0' => Event::Tick,
+                '\n' => Event::Input(Key::Enter),
+                '\u{1b}' => Event::Input(Key::Esc),
+                '\t' => Event::Input(Key::Tab),
+                '\u{17}' => Event::Input(Key::Ctrl('w')),
+                ch => Event::Input(Key::Char(ch)),
+            })
+            .collect();
+
+        let mut editor = Editor::with_backend(
+            MockBackend::new(Rect::new(40, 6)),
+            Box::new(MockLoop::new(events)),
+            document,
+        )
+        .unwrap();
+
+        // Running out of keys ends the run with an error.
+        let quit = editor.run().is_ok();
+        assert_eq!(quit, editor.should_quit);
+
+        editor
+    }
+
+    #[test]
+    fn test_save_if_modified_and_quit() {
+        let path = env::temp_dir().join("vie_test_editor_x.txt");
+        let path = path.to_str().unwrap();
+
+        fs::write(path, "text\n").unwrap();
+        let editor = run_editor(Document::open(path).unwrap(), "i!\u{1b}:x\n");
+        assert!(editor.should_quit);
+        assert_eq!(fs::read_to_string(path).unwrap(), "!text\n");
+
+        fs::write(path, "changed elsewhere\n").unwrap();
+        let editor = run_editor(Document::open(path).unwrap(), "ZZ");
+        assert!(editor.should_quit);
+        assert_eq!(fs::read_to_string(path).unwrap(), "changed elsewhere\n");
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_swap_is_written_for_unfocused_windows() {
+        let path = env::temp_dir().join("vie_test_editor_unfocused_swap.txt");
+        let document = Document::open(path.to_str().unwrap()).unwrap();
+        let swap = document.swap_file_name().unwrap();
+
+        let editor = run_editor(document, ":set ut=0\ni!\u{1b}:split\n\0");
+        assert_eq!(editor.windows.len(), 2);
+        assert_eq!(fs::read_to_string(&swap).unwrap(), "!\n");
+
+        fs::remove_file(swap).unwrap();
+    }
+
+    #[test]
+    fn test_quit_removes_swap_file() {
+        let path = env::temp_dir().join("vie_test_editor_quit_swap.txt");
+        let path = path.to_str().unwrap();
+
+        let document = Document::open(path).unwrap();
+        document.write_swap().unwrap();
+        let swap = document.swap_file_name().unwrap();
+
+        let editor = run_editor(document, "i!\u{1b}:q\n");
+        assert!(editor.should_quit);
+        assert!(!swap.exists());
+    }
+
+    #[test]
+    fn test_set_global_applies_to_new_windows_only() {
+        let split = env::temp_dir().join("vie_test_editor_setglobal_split.txt");
+        let existing = env::temp_dir().join("vie_test_editor_setglobal_existing.txt");
+        let (split, existing) = (split.to_str().unwrap(), existing.to_str().unwrap());
+
+        let keys =
+            format!(":setglobal noet\n:split {split}\ni\t\u{1b}:w\n\u{17}wi\t\u{1b}:w\n:q\n");
+        let editor = run_editor(Document::open(existing).unwrap(), &keys);
+        assert!(editor.should_quit);
+
+        assert_eq!(fs::read_to_string(split).unwrap(), "\t\n");
+        assert_eq!(fs::read_to_string(existing).unwrap(), "    \n");
+
+        fs::remove_file(split).unwrap();
+        fs::remove_file(existing).unwrap();
+    }
+
+    #[test]
+    fn test_set_global_editor_and_document_options() {
+        // With no update time the swap is due as soon as a key has been pressed.
+        let mut editor = run_editor(
+            Document::default(),
+            ":setglobal ut=0\n:setglobal fenc=latin1\n",
+        );
+        assert!(editor.autosave.tick(true));
+
+        editor.refresh_screen().unwrap();
+        assert!(editor
+            .terminal
+            .render_to_string()
+            .ends_with("E474: Invalid argument"));
+        assert_eq!(editor.global_options, Options::default());
+    }
+
+    #[test]
+    fn test_save_if_modified_and_quit_stays_open_without_file_name() {
+        let mut editor = run_editor(Document::from("text"), "i!\u{1b}ZZ");
+
+        assert!(!editor.should_quit);
+        assert!(editor.windows.focused().is_dirty());
+
+        editor.refresh_screen().unwrap();
+        assert!(editor
+            .terminal
+            .render_to_string()
+            .ends_with("E32: No file name"));
+    }
+
+    #[test]
+    fn test_save_error_message() {
+        assert_eq!(
+            save_error_message(&SaveError::NoFileName),
+            "E32: No file name"
+        );
+        assert_eq!(
+            save_error_message(&SaveError::ConversionFailed("a.txt".into(), 3)),
+            "\"a.txt\" E513: Write error, conversion failed in line 3"
+        );
+        assert_eq!(
+            save_error_message(&SaveError::NotFound("a/b.txt".into())),
+            "\"a/b.txt\" E212: Can't open file for writing: No such file or directory"
+        );
+    }
+}
@@ -8,10 +8,7 @@ use crossterm::{
     style::{Color as CrosstermColor, Print, SetBackgroundColor, SetForegroundColor},
     terminal::{Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen},
 };
-use std::{
-    io::{self, Write},
-    time::Duration,
-};
+use std::io::{self, Write};
 
 pub struct Backend<W: Write> {
     buffer: W,
@@ -109,11 +106,6 @@ impl<W: Write> BaseBackend for Backend<W> {
         Ok(())
     }
 
-    fn poll_events(&mut self, timeout: Duration) -> Result<bool, Error> {
-        let polled = crossterm::event::poll(timeout)?;
-        Ok(polled)
-    }
-
     fn position_cursor(&mut self, x: usize, y: usize) -> Result<(), Error> {
         use std::convert::TryFrom;
 
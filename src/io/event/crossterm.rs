@@ -2,14 +2,20 @@ use crate::io::event::{Event, Key, Loop as EventLoop};
 use anyhow::{Context, Error, Result};
 use crossterm::event::{self as ctevent, KeyCode, KeyEvent, KeyModifiers};
 use std::{
-    sync::mpsc::{self, Receiver},
-    thread,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, Sender},
+        Arc,
+    },
+    thread::{self, JoinHandle},
     time::Duration,
 };
 
 pub struct Loop {
     rx: Option<Receiver<Event>>,
     tick_rate: Duration,
+    stop: Arc<AtomicBool>,
+    worker: Option<JoinHandle<()>>,
 }
 
 impl Loop {
@@ -17,39 +23,29 @@ impl Loop {
         Self {
             rx: None,
             tick_rate,
+            stop: Arc::new(AtomicBool::new(false)),
+            worker: None,
         }
     }
+
+    /// Signals the worker thread to stop and waits for it to finish, returning false if it
+    /// panicked.
+    fn shutdown(&mut self) -> bool {
+        self.stop.store(true, Ordering::Relaxed);
+
+        self.worker
+            .take()
+            .is_none_or(|worker| worker.join().is_ok())
+    }
 }
 
 impl EventLoop for Loop {
     fn start(&mut self) {
         let (tx, rx) = mpsc::channel();
         let tick_rate = self.tick_rate;
+        let stop = Arc::clone(&self.stop);
 
-        thread::spawn(move || loop {
-            match ctevent::poll(tick_rate) {
-                Ok(true) => match ctevent::read() {
-                    Ok(ctevent::Event::Key(key)) => tx.send(Event::Input(Key::from(key))).unwrap(),
-                    Err(e) => {
-                        tx.send(Event::Error(Error::from(e).context("unable to read event")))
-                            .unwrap();
-
-                        break;
-                    }
-                    Ok(ctevent::Event::Mouse(_)) | Ok(ctevent::Event::Resize(_, _)) => {}
-                },
-                Ok(false) => tx.send(Event::Tick).unwrap(),
-                Err(e) => {
-                    tx.send(Event::Error(
-                        Error::from(e).context("unable to poll for events"),
-                    ))
-                    .unwrap();
-
-                    break;
-                }
-            };
-        });
-
+        self.worker = Some(thread::spawn(move || poll_events(&tx, tick_rate, &stop)));
         self.rx = Some(rx);
     }
 
@@ -63,6 +59,36 @@ impl EventLoop for Loop {
     }
 }
 
+impl Drop for Loop {
+    /// Stops the worker before the receiver is dropped, so that it is not left blocked in a
+    /// poll that would send to a closed channel.
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+/// Sends terminal events until told to stop, an error is reported, or the receiving end of
+/// the channel has gone away.
+fn poll_events(tx: &Sender<Event>, tick_rate: Duration, stop: &AtomicBool) {
+    while !stop.load(Ordering::Relaxed) {
+        let event = match ctevent::poll(tick_rate) {
+            Ok(true) => match ctevent::read() {
+                Ok(ctevent::Event::Key(key)) => Event::Input(Key::from(key)),
+                Ok(ctevent::Event::Mouse(_) | ctevent::Event::Resize(_, _)) => continue,
+                Err(e) => Event::Error(Error::from(e).context("unable to read event")),
+            },
+            Ok(false) => Event::Tick,
+            Err(e) => Event::Error(Error::from(e).context("unable to poll for events")),
+        };
+
+        let is_error = matches!(event, Event::Error(_));
+
+        if tx.send(event).is_err() || is_error {
+            break;
+        }
+    }
+}
+
 impl From<KeyEvent> for Key {
     fn from(event: KeyEvent) -> Self {
         match event {
@@ -134,3 +160,29 @@ impl From<KeyEvent> for Key {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_worker_stops_when_receiver_is_dropped() {
+        let (tx, rx) = mpsc::channel();
+        drop(rx);
+
+        let worker = thread::spawn(move || {
+            poll_events(&tx, Duration::from_millis(1), &AtomicBool::new(false));
+        });
+
+        assert!(worker.join().is_ok());
+    }
+
+    #[test]
+    fn test_dropping_loop_stops_worker() {
+        let mut event_loop = Loop::new(Duration::from_millis(1));
+        event_loop.start();
+
+        assert!(event_loop.shutdown());
+        drop(event_loop);
+    }
+}
@@ -0,0 +1,148 @@
+use crate::{
+    io::{
+        event::{Event, Loop as EventLoop},
+        Backend as BaseBackend,
+    },
+    ui::{layout::Rect, FrameBufferCell},
+};
+use anyhow::{anyhow, Error, Result};
+use std::{cell::RefCell, collections::VecDeque};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Call {
+    Clear,
+    Draw(Vec<FrameBufferCell>),
+    EnableRawMode,
+    EnterAlternateScreen,
+    DisableRawMode,
+    Flush,
+    LeaveAlternateScreen,
+    HideCursor,
+    PositionCursor(usize, usize),
+    ShowCursor,
+}
+
+/// A backend that records every call made to it instead of drawing to a terminal.
+pub struct Backend {
+    size: Rect,
+    calls: Vec<Call>,
+}
+
+impl Backend {
+    pub fn new(size: Rect) -> Self {
+        Self {
+            size,
+            calls: Vec::new(),
+        }
+    }
+
+    pub fn calls(&self) -> &[Call] {
+        &self.calls
+    }
+
+    pub fn clear_calls(&mut self) {
+        self.calls.clear();
+    }
+}
+
+impl BaseBackend for Backend {
+    fn clear(&mut self) -> Result<(), Error> {
+        self.calls.push(Call::Clear);
+        Ok(())
+    }
+
+    fn draw<'a, I>(&mut self, cells: I) -> Result<(), Error>
+    where
+        I: Iterator<Item = &'a FrameBufferCell>,
+    {
+        self.calls.push(Call::Draw(cells.cloned().collect()));
+        Ok(())
+    }
+
+    fn enable_raw_mode(&mut self) -> Result<(), Error> {
+        self.calls.push(Call::EnableRawMode);
+        Ok(())
+    }
+
+    fn enter_alterate_screen(&mut self) -> Result<(), Error> {
+        self.calls.push(Call::EnterAlternateScreen);
+        Ok(())
+    }
+
+    fn disable_raw_mode(&mut self) -> Result<(), Error> {
+        self.calls.push(Call::DisableRawMode);
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        self.calls.push(Call::Flush);
+        Ok(())
+    }
+
+    fn leave_alterante_screen(&mut self) -> Result<(), Error> {
+        self.calls.push(Call::LeaveAlternateScreen);
+        Ok(())
+    }
+
+    fn hide_cursor(&mut self) -> Result<(), Error> {
+        self.calls.push(Call::HideCursor);
+        Ok(())
+    }
+
+    fn position_cursor(&mut self, x: usize, y: usize) -> Result<(), Error> {
+        self.calls.push(Call::PositionCursor(x, y));
+        Ok(())
+    }
+
+    fn show_cursor(&mut self) -> Result<(), Error> {
+        self.calls.push(Call::ShowCursor);
+        Ok(())
+    }
+
+    fn size(&self) -> Result<Rect, Error> {
+        Ok(self.size)
+    }
+}
+
+/// An event loop that replays a fixed list of events.
+pub struct Loop {
+    events: RefCell<VecDeque<Event>>,
+}
+
+impl Loop {
+    pub fn new(events: Vec<Event>) -> Self {
+        Self {
+            events: RefCell::new(events.into()),
+        }
+    }
+}
+
+impl EventLoop for Loop {
+    fn start(&mut self) {}
+
+    fn next(&self) -> Result<Event> {
+        self.events
+            .borrow_mut()
+            .pop_front()
+            .ok_or_else(|| anyhow!("no more events"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::event::Key;
+
+    #[test]
+    fn test_loop_replays_events_in_order() {
+        let mut event_loop = Loop::new(vec![Event::Input(Key::Char('a')), Event::Tick]);
+        event_loop.start();
+
+        assert!(matches!(
+            event_loop.next(),
+            Ok(Event::Input(Key::Char('a')))
+        ));
+        assert!(matches!(event_loop.next(), Ok(Event::Tick)));
+        assert!(event_loop.next().is_err());
+    }
+}
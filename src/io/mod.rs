@@ -1,9 +1,10 @@
 use crate::ui::{layout::Rect, FrameBufferCell};
 use anyhow::{Error, Result};
-use std::time::Duration;
 
 mod crossterm;
 pub mod event;
+#[cfg(test)]
+pub mod mock;
 
 pub use self::crossterm::Backend as CrosstermBackend;
 
@@ -16,7 +17,6 @@ pub trait Backend {
     fn flush(&mut self) -> Result<(), Error>;
     fn leave_alterante_screen(&mut self) -> Result<(), Error>;
     fn hide_cursor(&mut self) -> Result<(), Error>;
-    fn poll_events(&mut self, timeout: Duration) -> Result<bool, Error>;
     fn position_cursor(&mut self, x: usize, y: usize) -> Result<(), Error>;
     fn show_cursor(&mut self) -> Result<(), Error>;
     fn size(&self) -> Result<Rect, Error>;
@@ -1,12 +1,16 @@
 #![warn(clippy::all, clippy::pedantic)]
+mod autosave;
 mod command_line;
 mod document;
 mod editor;
 mod io;
 mod ops;
+mod shell;
 mod status_bar; // TODO: move to submodule of Editor?
 mod terminal;
 mod ui;
+mod welcome;
+mod window_manager;
 
 use anyhow::Context;
 use editor::Editor;
@@ -19,7 +23,7 @@ fn main() {
             .context("an error occured while running the editor"),
         Err(e) => Err(e),
     } {
-        eprintln!("Error: {}", e);
+        eprintln!("Error: {e}");
         process::exit(1);
     }
 }
@@ -1,9 +1,13 @@
-use crate::{editor::Mode, ops::Command};
+use crate::{
+    editor::Mode,
+    ops::{Command, Operator},
+};
 use nom::{
     branch::alt,
+    bytes::complete::tag,
     character::complete::{char, digit0, one_of},
-    combinator::{all_consuming, map, recognize, value},
-    sequence::pair,
+    combinator::{all_consuming, map, map_res, opt, recognize, value},
+    sequence::{pair, preceded},
     IResult,
 };
 
@@ -15,6 +19,23 @@ fn insert_mode(input: &str) -> IResult<&str, Command> {
     value(Command::EnterMode(Mode::Insert), char('i'))(input)
 }
 
+fn join_lines(input: &str) -> IResult<&str, Command> {
+    value(Command::JoinLines, char('J'))(input)
+}
+
+fn delete_char(input: &str) -> IResult<&str, Command> {
+    value(Command::DeleteCharUnderCursor, char('x'))(input)
+}
+
+/// `.` repeats the last insert at the cursor.
+fn repeat_insert(input: &str) -> IResult<&str, Command> {
+    value(Command::InsertLastInserted, char('.'))(input)
+}
+
+fn delete_line(input: &str) -> IResult<&str, Command> {
+    value(Command::DeleteLine, tag("dd"))(input)
+}
+
 fn non_zero_digit(input: &str) -> IResult<&str, char> {
     one_of("123456789")(input)
 }
@@ -23,6 +44,23 @@ fn multiplier(input: &str) -> IResult<&str, &str> {
     recognize(pair(non_zero_digit, digit0))(input)
 }
 
+/// A count too large to be represented fails to parse, so the command it prefixes is
+/// dropped.
+fn count(input: &str) -> IResult<&str, usize> {
+    map_res(multiplier, str::parse)(input)
+}
+
+/// Allows the command to be prefixed with a count, repeating it that many times.
+fn counted<'a, F>(command: F) -> impl FnMut(&'a str) -> IResult<&'a str, Command>
+where
+    F: FnMut(&'a str) -> IResult<&'a str, Command>,
+{
+    map(pair(opt(count), command), |(n, command)| match n {
+        Some(n) => Command::Repeat(n, Box::new(command)),
+        None => command,
+    })
+}
+
 fn movement_key(input: &str) -> IResult<&str, char> {
     alt((char('h'), char('j'), char('k'), char('l')))(input)
 }
@@ -37,26 +75,188 @@ fn single_move_action(input: &str) -> IResult<&str, Command> {
     })(input)
 }
 
-fn multi_move_action(input: &str) -> IResult<&str, Command> {
-    map(pair(multiplier, movement_key), |(m, c)| match c {
-        'h' => Command::MoveCursorLeft(m.parse::<usize>().unwrap()),
-        'j' => Command::MoveCursorDown(m.parse::<usize>().unwrap()),
-        'k' => Command::MoveCursorUp(m.parse::<usize>().unwrap()),
-        'l' => Command::MoveCursorRight(m.parse::<usize>().unwrap()),
+fn movement_action(input: &str) -> IResult<&str, Command> {
+    counted(single_move_action)(input)
+}
+
+fn display_line_action(input: &str) -> IResult<&str, Command> {
+    map(preceded(char('g'), one_of("jk")), |c| match c {
+        'j' => Command::MoveCursorDisplayLineDown(1),
+        'k' => Command::MoveCursorDisplayLineUp(1),
         _ => unreachable!(),
     })(input)
 }
 
-fn movement_action(input: &str) -> IResult<&str, Command> {
-    alt((single_move_action, multi_move_action))(input)
+fn edit_action(input: &str) -> IResult<&str, Command> {
+    counted(alt((
+        join_lines,
+        delete_char,
+        delete_line,
+        display_line_action,
+        repeat_insert,
+    )))(input)
+}
+
+/// Repeating the last key of an operator (as in `gqq`) applies it to the current line.
+fn linewise_motion<'a>(key: char) -> impl FnMut(&'a str) -> IResult<&'a str, Command> {
+    value(Command::MoveCursorDown(0), char(key))
+}
+
+fn reflow_action(input: &str) -> IResult<&str, Command> {
+    map(
+        preceded(tag("gq"), alt((linewise_motion('q'), movement_action))),
+        |motion| Command::Operate(Operator::Reflow, Box::new(motion)),
+    )(input)
+}
+
+fn comment_action(input: &str) -> IResult<&str, Command> {
+    map(
+        preceded(tag("gc"), alt((linewise_motion('c'), movement_action))),
+        |motion| Command::Operate(Operator::ToggleComment, Box::new(motion)),
+    )(input)
+}
+
+fn fold_action(input: &str) -> IResult<&str, Command> {
+    alt((
+        map(preceded(tag("zf"), movement_action), |motion| {
+            Command::Operate(Operator::Fold, Box::new(motion))
+        }),
+        value(Command::ToggleFold, tag("za")),
+        value(Command::DeleteFold, tag("zd")),
+    ))(input)
+}
+
+fn quit_action(input: &str) -> IResult<&str, Command> {
+    alt((
+        value(Command::SaveIfModifiedAndQuit, tag("ZZ")),
+        value(Command::Quit, tag("ZQ")),
+    ))(input)
+}
+
+fn pending_sequence(input: &str) -> IResult<&str, &str> {
+    alt((
+        recognize(pair(
+            alt((tag("gq"), tag("gc"), tag("zf"))),
+            opt(multiplier),
+        )),
+        recognize(pair(opt(multiplier), one_of("gdz"))),
+        tag("Z"),
+        multiplier,
+    ))(input)
 }
 
 pub fn parse(input: &str) -> Option<Command> {
-    if let Ok((_, command)) =
-        all_consuming(alt((command_mode, insert_mode, movement_action)))(input)
+    if let Ok((_, command)) = all_consuming(alt((
+        command_mode,
+        insert_mode,
+        movement_action,
+        edit_action,
+        reflow_action,
+        comment_action,
+        fold_action,
+        quit_action,
+    )))(input)
     {
         return Some(command);
     }
 
     None
 }
+
+/// Whether the input is the start of a command that needs more keys to complete.
+pub fn is_pending(input: &str) -> bool {
+    all_consuming(pending_sequence)(input).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let tests = vec![
+            ("j", Command::MoveCursorDown(1)),
+            ("J", Command::JoinLines),
+            ("x", Command::DeleteCharUnderCursor),
+            ("dd", Command::DeleteLine),
+            (".", Command::InsertLastInserted),
+            (
+                "2.",
+                Command::Repeat(2, Box::new(Command::InsertLastInserted)),
+            ),
+            ("gj", Command::MoveCursorDisplayLineDown(1)),
+            (
+                "3x",
+                Command::Repeat(3, Box::new(Command::DeleteCharUnderCursor)),
+            ),
+            (
+                "5j",
+                Command::Repeat(5, Box::new(Command::MoveCursorDown(1))),
+            ),
+            ("2dd", Command::Repeat(2, Box::new(Command::DeleteLine))),
+            (
+                "2gk",
+                Command::Repeat(2, Box::new(Command::MoveCursorDisplayLineUp(1))),
+            ),
+            (
+                "gqq",
+                Command::Operate(Operator::Reflow, Box::new(Command::MoveCursorDown(0))),
+            ),
+            (
+                "gq2j",
+                Command::Operate(
+                    Operator::Reflow,
+                    Box::new(Command::Repeat(2, Box::new(Command::MoveCursorDown(1)))),
+                ),
+            ),
+            (
+                "gcc",
+                Command::Operate(
+                    Operator::ToggleComment,
+                    Box::new(Command::MoveCursorDown(0)),
+                ),
+            ),
+            (
+                "gc2k",
+                Command::Operate(
+                    Operator::ToggleComment,
+                    Box::new(Command::Repeat(2, Box::new(Command::MoveCursorUp(1)))),
+                ),
+            ),
+            (
+                "zf2j",
+                Command::Operate(
+                    Operator::Fold,
+                    Box::new(Command::Repeat(2, Box::new(Command::MoveCursorDown(1)))),
+                ),
+            ),
+            ("za", Command::ToggleFold),
+            ("zd", Command::DeleteFold),
+            ("ZZ", Command::SaveIfModifiedAndQuit),
+            ("ZQ", Command::Quit),
+        ];
+
+        for (input, command) in tests {
+            assert_eq!(parse(input), Some(command));
+        }
+    }
+
+    #[test]
+    fn test_parse_overflowing_count() {
+        assert_eq!(parse("99999999999999999999j"), None);
+        assert_eq!(parse("gq99999999999999999999j"), None);
+    }
+
+    #[test]
+    fn test_is_pending() {
+        for input in &[
+            "3", "g", "3g", "d", "2d", "gq", "gq2", "gc", "gc3", "z", "zf", "zf2", "Z",
+        ] {
+            assert!(is_pending(input), "{} should be pending", input);
+        }
+
+        for input in &["x", "gx", "dx", "gqq", "gcc", "za", "zx", "ZZ", "Zx"] {
+            assert!(!is_pending(input), "{} should not be pending", input);
+        }
+    }
+}
@@ -1,21 +1,26 @@
 use crate::{editor::Mode, io::event::Key, ops::Command};
 
+#[derive(Default)]
 pub struct Parser {
     input_buffer: String,
-}
-
-impl Default for Parser {
-    fn default() -> Self {
-        Self {
-            input_buffer: String::new(),
-        }
-    }
+    window_prefix_pending: bool,
 }
 
 impl Parser {
     pub fn matched_command_for(&mut self, key: Key, mode: Mode) -> Option<Command> {
         match mode {
             Mode::Normal => {
+                if self.window_prefix_pending {
+                    self.window_prefix_pending = false;
+                    return window_command_for_key_press(key);
+                }
+
+                if let Key::Ctrl('w') = key {
+                    self.window_prefix_pending = true;
+                    self.input_buffer.clear();
+                    return None;
+                }
+
                 if let Key::Char(ch) = key {
                     self.input_buffer.push(ch);
                 }
@@ -27,7 +32,13 @@ impl Parser {
                 normal_mode_command_for_key_press(key).map_or_else(
                     || {
                         let command = normal_mode_command_for_input_sequence(&self.input_buffer);
-                        self.input_buffer.clear();
+
+                        if command.is_some()
+                            || !normal_mode_input_sequence_is_pending(&self.input_buffer)
+                        {
+                            self.input_buffer.clear();
+                        }
+
                         command
                     },
                     Some,
@@ -47,6 +58,20 @@ fn normal_mode_command_for_key_press(key: Key) -> Option<Command> {
         Key::PageDown => Some(Command::MoveCursorPageDown),
         Key::Insert => Some(Command::EnterMode(Mode::Insert)),
         Key::Enter => Some(Command::MoveCursorDown(1)),
+        Key::Ctrl('g') => Some(Command::ShowFileInfo),
+        _ => None,
+    }
+}
+
+fn window_command_for_key_press(key: Key) -> Option<Command> {
+    match key {
+        Key::Char('w') | Key::Ctrl('w') => Some(Command::FocusNextWindow),
+        Key::Char('j') | Key::Down => Some(Command::FocusWindowBelow),
+        Key::Char('k') | Key::Up => Some(Command::FocusWindowAbove),
+        Key::Char('c') => Some(Command::CloseWindow),
+        Key::Char('o') => Some(Command::CloseOtherWindows),
+        Key::Char('+') => Some(Command::IncreaseWindowHeight(1)),
+        Key::Char('-') => Some(Command::DecreaseWindowHeight(1)),
         _ => None,
     }
 }
@@ -64,6 +89,10 @@ fn insert_mode_command_for_key_press(key: Key) -> Option<Command> {
         Key::Delete => Some(Command::DeleteCharForward),
         Key::Backspace => Some(Command::DeleteCharBackward),
         Key::Enter => Some(Command::InsertLineBreak),
+        Key::Tab => Some(Command::InsertTab),
+        Key::Ctrl('t') => Some(Command::IndentLine),
+        Key::Ctrl('d') => Some(Command::DedentLine),
+        Key::Ctrl('a') => Some(Command::InsertLastInserted),
         Key::Char(ch) => Some(Command::InsertChar(ch)),
         Key::Esc => Some(Command::EnterMode(Mode::Normal)),
         _ => None,
@@ -73,3 +102,43 @@ fn insert_mode_command_for_key_press(key: Key) -> Option<Command> {
 fn normal_mode_command_for_input_sequence(sequence: &str) -> Option<Command> {
     super::normal::parse(sequence)
 }
+
+fn normal_mode_input_sequence_is_pending(sequence: &str) -> bool {
+    super::normal::is_pending(sequence)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_window_prefix() {
+        let mut parser = Parser::default();
+
+        assert_eq!(
+            parser.matched_command_for(Key::Ctrl('w'), Mode::Normal),
+            None
+        );
+        assert_eq!(
+            parser.matched_command_for(Key::Char('j'), Mode::Normal),
+            Some(Command::FocusWindowBelow)
+        );
+        assert_eq!(
+            parser.matched_command_for(Key::Char('j'), Mode::Normal),
+            Some(Command::MoveCursorDown(1))
+        );
+
+        assert_eq!(
+            parser.matched_command_for(Key::Ctrl('w'), Mode::Normal),
+            None
+        );
+        assert_eq!(
+            parser.matched_command_for(Key::Char('x'), Mode::Normal),
+            None
+        );
+        assert_eq!(
+            parser.matched_command_for(Key::Char('k'), Mode::Normal),
+            Some(Command::MoveCursorUp(1))
+        );
+    }
+}
@@ -1,25 +1,92 @@
-use crate::editor::Mode;
+use crate::{document::Encoding, editor::Mode};
+use std::time::Duration;
 
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub enum Command {
     EnterMode(Mode),
 
+    Repeat(usize, Box<Command>),
+
     InsertChar(char),
+    InsertTab,
     InsertLineBreak,
+    InsertLastInserted,
     DeleteCharForward,
+    DeleteCharUnderCursor,
     DeleteCharBackward,
+    DeleteWordBackward,
+    DeleteToLineStart,
+    DeleteLine,
+    IndentLine,
+    DedentLine,
+    JoinLines,
+    ToggleFold,
+    DeleteFold,
 
     MoveCursorUp(usize),
     MoveCursorDown(usize),
     MoveCursorLeft(usize),
     MoveCursorRight(usize),
+    MoveCursorDisplayLineUp(usize),
+    MoveCursorDisplayLineDown(usize),
     MoveCursorLineStart,
     MoveCursorLineEnd,
     MoveCursorPageUp,
     MoveCursorPageDown,
 
+    Operate(Operator, Box<Command>),
+    Global(String, Box<Command>),
+    InvertedGlobal(String, Box<Command>),
+    Filter(LineRange, String),
+
+    Set(Setting),
+    SetGlobal(Setting),
+
     Save,
     SaveAs(String),
+    SetFileName(String),
+    ShowFileInfo,
+
+    Split(Option<String>),
+    FocusNextWindow,
+    FocusWindowBelow,
+    FocusWindowAbove,
+    CloseWindow,
+    CloseOtherWindows,
+    IncreaseWindowHeight(usize),
+    DecreaseWindowHeight(usize),
 
     Quit,
+    SaveIfModifiedAndQuit,
+}
+
+/// The lines an ex command applies to.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum LineRange {
+    /// `.`, the line the cursor is on.
+    Current,
+    /// `%`, every line in the document.
+    All,
+    /// `n,m`, the lines numbered `n` to `m`, counting from 1.
+    Lines(usize, usize),
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Operator {
+    Reflow,
+    ToggleComment,
+    Fold,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Setting {
+    TextWidth(usize),
+    ShiftWidth(usize),
+    TabStop(usize),
+    ExpandTab(bool),
+    EndOfBufferChar(char),
+    Wrap(bool),
+    HighlightTrailingWhitespace(bool),
+    FileEncoding(Encoding),
+    UpdateTime(Duration),
 }
@@ -1,12 +1,18 @@
-use crate::{io::event::Key, ops::Command};
+use crate::{
+    document::Encoding,
+    io::event::Key,
+    ops::{Command, LineRange, Setting},
+};
 use nom::{
     branch::alt,
-    character::complete::{anychar, char},
-    combinator::{all_consuming, map, value},
+    bytes::complete::{is_not, tag},
+    character::complete::{anychar, char, digit1},
+    combinator::{all_consuming, map, map_res, opt, value},
     multi::many1,
-    sequence::{pair, separated_pair},
+    sequence::{delimited, pair, preceded, separated_pair},
     IResult,
 };
+use std::{convert::TryFrom, time::Duration};
 
 pub fn command_for_key(key: Key) -> Option<Command> {
     match key {
@@ -15,6 +21,8 @@ pub fn command_for_key(key: Key) -> Option<Command> {
         Key::Right => Some(Command::MoveCursorRight(1)),
         Key::Backspace => Some(Command::DeleteCharBackward),
         Key::Delete => Some(Command::DeleteCharForward),
+        Key::Ctrl('w') => Some(Command::DeleteWordBackward),
+        Key::Ctrl('u') => Some(Command::DeleteToLineStart),
         Key::Home => Some(Command::MoveCursorLineStart),
         Key::End => Some(Command::MoveCursorLineEnd),
         Key::Esc => Some(Command::EnterMode(crate::editor::Mode::Normal)),
@@ -26,6 +34,11 @@ pub fn quit(input: &str) -> IResult<&str, Command> {
     value(Command::Quit, all_consuming(char('q')))(input)
 }
 
+/// Parses `x`, which writes the buffer only when it has unsaved changes before quitting.
+pub fn save_if_modified_and_quit(input: &str) -> IResult<&str, Command> {
+    value(Command::SaveIfModifiedAndQuit, all_consuming(char('x')))(input)
+}
+
 pub fn save(input: &str) -> IResult<&str, Command> {
     value(Command::Save, all_consuming(char('w')))(input)
 }
@@ -37,8 +50,199 @@ pub fn save_as(input: &str) -> IResult<&str, Command> {
     )(input)
 }
 
+pub fn file(input: &str) -> IResult<&str, Command> {
+    map(
+        preceded(alt((tag("file "), tag("f "))), many1(anychar)),
+        |name| Command::SetFileName(name.into_iter().collect::<String>()),
+    )(input)
+}
+
+pub fn split(input: &str) -> IResult<&str, Command> {
+    map(
+        preceded(
+            alt((tag("split"), tag("sp"))),
+            opt(preceded(char(' '), many1(anychar))),
+        ),
+        |name| Command::Split(name.map(|name| name.into_iter().collect::<String>())),
+    )(input)
+}
+
+/// Parses `g/pattern/d` and its inverse `v/pattern/d` (or `g!/pattern/d`).
+pub fn global(input: &str) -> IResult<&str, Command> {
+    map(
+        pair(
+            alt((
+                value(true, tag("g!")),
+                value(false, char('g')),
+                value(true, char('v')),
+            )),
+            pair(
+                delimited(char('/'), is_not("/"), char('/')),
+                value(Command::DeleteLine, all_consuming(char('d'))),
+            ),
+        ),
+        |(inverted, (pattern, command)): (bool, (&str, Command))| {
+            if inverted {
+                Command::InvertedGlobal(pattern.into(), Box::new(command))
+            } else {
+                Command::Global(pattern.into(), Box::new(command))
+            }
+        },
+    )(input)
+}
+
+fn number(input: &str) -> IResult<&str, usize> {
+    map_res(digit1, str::parse)(input)
+}
+
+fn line_range(input: &str) -> IResult<&str, LineRange> {
+    alt((
+        value(LineRange::All, char('%')),
+        value(LineRange::Current, char('.')),
+        map(separated_pair(number, char(','), number), |(start, end)| {
+            LineRange::Lines(start, end)
+        }),
+        map(number, |line| LineRange::Lines(line, line)),
+    ))(input)
+}
+
+/// Parses `{range}!command`, which pipes the lines through the shell command and replaces
+/// them with its output. Without a range `!` only runs a command, which is not supported.
+pub fn filter(input: &str) -> IResult<&str, Command> {
+    map(
+        separated_pair(line_range, char('!'), many1(anychar)),
+        |(range, command)| Command::Filter(range, command.into_iter().collect::<String>()),
+    )(input)
+}
+
+fn text_width(input: &str) -> IResult<&str, Setting> {
+    map(
+        preceded(pair(alt((tag("textwidth"), tag("tw"))), char('=')), number),
+        Setting::TextWidth,
+    )(input)
+}
+
+fn shift_width(input: &str) -> IResult<&str, Setting> {
+    map(
+        preceded(pair(alt((tag("shiftwidth"), tag("sw"))), char('=')), number),
+        Setting::ShiftWidth,
+    )(input)
+}
+
+fn tab_stop(input: &str) -> IResult<&str, Setting> {
+    map(
+        preceded(pair(alt((tag("tabstop"), tag("ts"))), char('=')), number),
+        Setting::TabStop,
+    )(input)
+}
+
+fn expand_tab(input: &str) -> IResult<&str, Setting> {
+    alt((
+        value(Setting::ExpandTab(true), alt((tag("expandtab"), tag("et")))),
+        value(
+            Setting::ExpandTab(false),
+            alt((tag("noexpandtab"), tag("noet"))),
+        ),
+    ))(input)
+}
+
+fn fill_chars(input: &str) -> IResult<&str, Setting> {
+    map(
+        preceded(
+            tag("fillchars=eob:"),
+            alt((value(' ', tag("\\ ")), anychar)),
+        ),
+        Setting::EndOfBufferChar,
+    )(input)
+}
+
+fn wrap(input: &str) -> IResult<&str, Setting> {
+    alt((
+        value(Setting::Wrap(true), tag("wrap")),
+        value(Setting::Wrap(false), tag("nowrap")),
+    ))(input)
+}
+
+fn highlight_trailing_whitespace(input: &str) -> IResult<&str, Setting> {
+    alt((
+        value(
+            Setting::HighlightTrailingWhitespace(true),
+            tag("highlight_trailing_whitespace"),
+        ),
+        value(
+            Setting::HighlightTrailingWhitespace(false),
+            tag("nohighlight_trailing_whitespace"),
+        ),
+    ))(input)
+}
+
+fn file_encoding(input: &str) -> IResult<&str, Setting> {
+    map(
+        preceded(
+            pair(alt((tag("fileencoding"), tag("fenc"))), char('=')),
+            alt((
+                value(Encoding::Utf8, alt((tag("utf-8"), tag("utf8")))),
+                value(Encoding::Latin1, tag("latin1")),
+            )),
+        ),
+        Setting::FileEncoding,
+    )(input)
+}
+
+fn update_time(input: &str) -> IResult<&str, Setting> {
+    map(
+        preceded(pair(alt((tag("updatetime"), tag("ut"))), char('=')), number),
+        |millis| {
+            Setting::UpdateTime(Duration::from_millis(
+                u64::try_from(millis).unwrap_or(u64::MAX),
+            ))
+        },
+    )(input)
+}
+
+fn setting(input: &str) -> IResult<&str, Setting> {
+    alt((
+        text_width,
+        shift_width,
+        tab_stop,
+        expand_tab,
+        fill_chars,
+        wrap,
+        highlight_trailing_whitespace,
+        file_encoding,
+        update_time,
+    ))(input)
+}
+
+/// Parses `set option`, which changes the option for the current buffer only.
+pub fn set(input: &str) -> IResult<&str, Command> {
+    map(preceded(tag("set "), all_consuming(setting)), Command::Set)(input)
+}
+
+/// Parses `setglobal option`, which changes the option new buffers start with.
+pub fn set_global(input: &str) -> IResult<&str, Command> {
+    map(
+        preceded(tag("setglobal "), all_consuming(setting)),
+        Command::SetGlobal,
+    )(input)
+}
+
 pub fn command_for_input(input: &str) -> Option<Command> {
-    if let Ok((_, (_, command))) = all_consuming(pair(char(':'), alt((quit, save, save_as))))(input)
+    if let Ok((_, (_, command))) = all_consuming(pair(
+        char(':'),
+        alt((
+            quit,
+            save_if_modified_and_quit,
+            save,
+            save_as,
+            set,
+            set_global,
+            split,
+            file,
+            global,
+            filter,
+        )),
+    ))(input)
     {
         return Some(command);
     }
@@ -55,10 +259,76 @@ mod tests {
         let tests = vec![
             (":q", Command::Quit),
             (":w", Command::Save),
+            (":x", Command::SaveIfModifiedAndQuit),
+            (
+                ":%!sort -r",
+                Command::Filter(LineRange::All, "sort -r".into()),
+            ),
+            (
+                ":2,4!fmt",
+                Command::Filter(LineRange::Lines(2, 4), "fmt".into()),
+            ),
+            (
+                ":.!date",
+                Command::Filter(LineRange::Current, "date".into()),
+            ),
+            (
+                ":3!wc",
+                Command::Filter(LineRange::Lines(3, 3), "wc".into()),
+            ),
             (":w some_file.txt", Command::SaveAs("some_file.txt".into())),
+            (":set tw=40", Command::Set(Setting::TextWidth(40))),
+            (":set sw=2", Command::Set(Setting::ShiftWidth(2))),
+            (":set tabstop=8", Command::Set(Setting::TabStop(8))),
+            (":set wrap", Command::Set(Setting::Wrap(true))),
+            (":set noet", Command::Set(Setting::ExpandTab(false))),
+            (
+                ":setglobal expandtab",
+                Command::SetGlobal(Setting::ExpandTab(true)),
+            ),
+            (
+                ":set updatetime=300",
+                Command::Set(Setting::UpdateTime(Duration::from_millis(300))),
+            ),
+            (":set nowrap", Command::Set(Setting::Wrap(false))),
+            (
+                ":set highlight_trailing_whitespace",
+                Command::Set(Setting::HighlightTrailingWhitespace(true)),
+            ),
+            (
+                ":set nohighlight_trailing_whitespace",
+                Command::Set(Setting::HighlightTrailingWhitespace(false)),
+            ),
+            (
+                ":set fenc=latin1",
+                Command::Set(Setting::FileEncoding(Encoding::Latin1)),
+            ),
+            (
+                ":set fillchars=eob:\\ ",
+                Command::Set(Setting::EndOfBufferChar(' ')),
+            ),
+            (
+                ":set fillchars=eob:-",
+                Command::Set(Setting::EndOfBufferChar('-')),
+            ),
+            (
+                ":g/TODO/d",
+                Command::Global("TODO".into(), Box::new(Command::DeleteLine)),
+            ),
+            (
+                ":v/TODO/d",
+                Command::InvertedGlobal("TODO".into(), Box::new(Command::DeleteLine)),
+            ),
+            (
+                ":g!/TODO/d",
+                Command::InvertedGlobal("TODO".into(), Box::new(Command::DeleteLine)),
+            ),
+            (":file foo.py", Command::SetFileName("foo.py".into())),
+            (":sp", Command::Split(None)),
+            (":split notes.txt", Command::Split(Some("notes.txt".into()))),
         ];
 
-        for (input, command) in tests.into_iter() {
+        for (input, command) in tests {
             assert_eq!(command_for_input(input), Some(command));
         }
     }
@@ -83,4 +353,13 @@ mod tests {
             Ok(("", Command::SaveAs("test.txt".into())))
         );
     }
+
+    #[test]
+    fn test_set() {
+        assert!(set("set tw=").is_err());
+        assert_eq!(
+            set("set textwidth=20"),
+            Ok(("", Command::Set(Setting::TextWidth(20))))
+        );
+    }
 }
@@ -2,4 +2,4 @@ pub mod buffer;
 mod command;
 pub mod command_line;
 
-pub use command::Command;
+pub use command::{Command, LineRange, Operator, Setting};
@@ -0,0 +1,101 @@
+use std::{
+    io::{self, Write},
+    process::{Command, Stdio},
+    thread,
+};
+use thiserror::Error as ThisError;
+
+#[derive(Debug, ThisError)]
+pub enum FilterError {
+    #[error("unable to run \"{0}\": {1}")]
+    Spawn(String, #[source] io::Error),
+    #[error("shell returned {0}{}", first_line(.1))]
+    Failed(i32, String),
+    #[error("shell was terminated by a signal")]
+    Terminated,
+}
+
+/// The first line of what a failed command wrote to standard error, to follow its exit code.
+fn first_line(stderr: &str) -> String {
+    stderr
+        .lines()
+        .next()
+        .map_or_else(String::new, |line| format!(": {line}"))
+}
+
+/// Runs the command through the shell with `input` on its standard input, returning what it
+/// wrote to its standard output. Standard error is captured rather than left to end up on
+/// the editor's screen, and reported when the command fails.
+pub fn filter(command: &str, input: &str) -> Result<String, FilterError> {
+    let spawn_error = |e| FilterError::Spawn(command.into(), e);
+
+    let mut child = shell_command(command)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(spawn_error)?;
+
+    // The input is written from another thread so that a command which starts writing its
+    // output before it has read all of its input can not fill the pipe and block forever.
+    let mut stdin = child
+        .stdin
+        .take()
+        .expect("stdin of filter command is piped");
+    let input = input.to_string();
+    let writer = thread::spawn(move || stdin.write_all(input.as_bytes()));
+
+    let output = child.wait_with_output().map_err(spawn_error)?;
+
+    // A command that exits without reading all of its input breaks the pipe, which only
+    // matters if the command also failed.
+    let _ = writer.join();
+
+    match output.status.code() {
+        Some(0) => Ok(String::from_utf8_lossy(&output.stdout).into_owned()),
+        Some(code) => Err(FilterError::Failed(
+            code,
+            String::from_utf8_lossy(&output.stderr).into_owned(),
+        )),
+        None => Err(FilterError::Terminated),
+    }
+}
+
+#[cfg(unix)]
+fn shell_command(command: &str) -> Command {
+    let mut shell = Command::new("sh");
+    shell.arg("-c").arg(command);
+    shell
+}
+
+#[cfg(windows)]
+fn shell_command(command: &str) -> Command {
+    let mut shell = Command::new("cmd");
+    shell.arg("/C").arg(command);
+    shell
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filter_pipes_input_through_command() {
+        assert_eq!(filter("tr a-z A-Z", "one\ntwo\n").unwrap(), "ONE\nTWO\n");
+    }
+
+    #[test]
+    fn test_filter_reports_failure() {
+        assert!(matches!(
+            filter("cat > /dev/null; exit 3", "ignored\n"),
+            Err(FilterError::Failed(3, _))
+        ));
+    }
+
+    #[test]
+    fn test_filter_reports_first_line_of_error_output() {
+        let error = filter("echo oops >&2; echo more >&2; exit 2", "").unwrap_err();
+
+        assert_eq!(error.to_string(), "shell returned 2: oops");
+    }
+}
@@ -1,17 +1,23 @@
 use crate::{
+    document::Buffer,
     editor::Mode,
-    ui::layout::{Component, Position, Rect},
+    ui::layout::{Component, Rect},
     ui::style::{Color, Style},
     ui::FrameBuffer,
 };
+use unicode_segmentation::UnicodeSegmentation;
 
+const SEPARATOR: &str = "    ";
+
+pub type Segment = (String, Style);
+
+/// A single line made of left aligned and right aligned segments. When the line is too narrow
+/// to fit everything, right segments are dropped first, starting with the innermost one.
 #[derive(Default)]
 pub struct StatusBar {
     viewport: Rect,
-    mode: Mode,
-    line_count: usize,
-    cursor_position: Position,
-    file_name: String,
+    left: Vec<Segment>,
+    right: Vec<Segment>,
 }
 
 impl StatusBar {
@@ -22,43 +28,150 @@ impl StatusBar {
         }
     }
 
-    pub fn update(
-        &mut self,
-        mode: Mode,
-        line_count: usize,
-        cursor_position: Position,
-        file_name: &str,
-    ) {
-        self.mode = mode;
-        self.line_count = line_count;
-        self.cursor_position = cursor_position;
-        self.file_name = file_name.into();
+    /// Shows the mode and the state of the buffer.
+    pub fn update(&mut self, mode: Mode, buffer: &Buffer) {
+        let style = default_style();
+        let cursor_position = buffer.document_cursor_position();
+
+        let mut left = vec![
+            (format!("Mode: [{mode}]"), style.clone()),
+            (format!("File: {}", buffer.document_name()), style.clone()),
+        ];
+
+        if buffer.is_dirty() {
+            left.push(("[+]".into(), style.clone()));
+        }
+
+        let right = vec![
+            (buffer.file_type().to_string(), style.clone()),
+            (buffer.encoding().to_string(), style.clone()),
+            (
+                format!(
+                    "L: {}/{} C: {}",
+                    cursor_position.y + 1,
+                    buffer.lines_in_document(),
+                    cursor_position.x + 1
+                ),
+                style,
+            ),
+        ];
+
+        self.set_segments(left, right);
+    }
+
+    pub fn set_segments(&mut self, left: Vec<Segment>, right: Vec<Segment>) {
+        self.left = left;
+        self.right = right;
+    }
+
+    /// The column each visible segment starts at.
+    fn layout(&self) -> Vec<(usize, &Segment)> {
+        let width = self.viewport.width;
+        let mut layout = Vec::new();
+
+        let mut x = 0;
+        for segment in &self.left {
+            layout.push((x, segment));
+            x += text_width(&segment.0) + SEPARATOR.len();
+        }
+        let left_width = x.saturating_sub(SEPARATOR.len());
+
+        let mut right = &self.right[..];
+        while !right.is_empty() && left_width + 1 + segments_width(right) > width {
+            right = &right[1..];
+        }
+
+        let mut x = width.saturating_sub(segments_width(right));
+        for segment in right {
+            layout.push((x, segment));
+            x += text_width(&segment.0) + SEPARATOR.len();
+        }
+
+        layout
     }
 }
 
+fn default_style() -> Style {
+    Style::new(Color::Rgb(63, 63, 63), Color::Rgb(239, 239, 239))
+}
+
+fn text_width(text: &str) -> usize {
+    text.graphemes(true).count()
+}
+
+fn segments_width(segments: &[Segment]) -> usize {
+    segments
+        .iter()
+        .map(|(text, _)| text_width(text))
+        .sum::<usize>()
+        + SEPARATOR.len() * segments.len().saturating_sub(1)
+}
+
 impl Component for StatusBar {
     fn render(&self, buffer: &mut FrameBuffer) {
-        let mut status = format!("Mode: [{}]    File: {}", self.mode, self.file_name);
-        let line_indicator = format!(
-            "L: {}/{} C: {}",
-            self.cursor_position.y,
-            self.line_count,
-            self.cursor_position.x + 1
-        );
+        let line = self.viewport.top();
 
-        let len = status.len() + line_indicator.len();
+        buffer.write_line(line, &" ".repeat(self.viewport.width), &default_style());
 
-        if self.viewport.width > len {
-            status.push_str(&" ".repeat(self.viewport.width - len));
+        for (x, (text, style)) in self.layout() {
+            buffer.write_at(self.viewport.left() + x, line, text, style);
         }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn status_bar(width: usize) -> StatusBar {
+        let mut status_bar = StatusBar::new(Rect::new(width, 1));
+        let style = default_style();
+
+        status_bar.set_segments(
+            vec![("left".into(), style.clone())],
+            vec![("inner".into(), style.clone()), ("outer".into(), style)],
+        );
 
-        status = format!("{}{}", status, line_indicator);
-        status.truncate(self.viewport.width);
+        status_bar
+    }
+
+    fn positions(status_bar: &StatusBar) -> Vec<(usize, &str)> {
+        status_bar
+            .layout()
+            .into_iter()
+            .map(|(x, (text, _))| (x, &text[..]))
+            .collect()
+    }
+
+    #[test]
+    fn test_right_segments_are_right_aligned() {
+        assert_eq!(
+            positions(&status_bar(30)),
+            vec![(0, "left"), (16, "inner"), (25, "outer")]
+        );
+    }
 
-        buffer.write_line(
-            self.viewport.top(),
-            &status,
-            &Style::new(Color::Rgb(63, 63, 63), Color::Rgb(239, 239, 239)),
+    #[test]
+    fn test_right_segments_are_dropped_first_when_narrow() {
+        assert_eq!(
+            positions(&status_bar(19)),
+            vec![(0, "left"), (5, "inner"), (14, "outer")]
         );
+        assert_eq!(positions(&status_bar(18)), vec![(0, "left"), (13, "outer")]);
+        assert_eq!(positions(&status_bar(9)), vec![(0, "left")]);
+    }
+
+    #[test]
+    fn test_long_left_segments_are_clipped() {
+        let mut frame = FrameBuffer::empty(Rect::new(3, 1));
+
+        status_bar(3).render(&mut frame);
+
+        let symbols: Vec<&str> = FrameBuffer::empty(Rect::new(3, 1))
+            .diff(&frame)
+            .into_iter()
+            .map(|cell| &cell.symbol()[..])
+            .collect();
+        assert_eq!(symbols, vec!["l", "e", "f"]);
     }
 }
@@ -12,7 +12,7 @@ pub struct Frame<'a, B: Backend> {
     terminal: &'a mut Terminal<B>,
 }
 
-impl<'a, B: Backend> Frame<'a, B> {
+impl<B: Backend> Frame<'_, B> {
     pub fn cursor_position(&self) -> &Position {
         &self.cursor_position
     }
@@ -31,6 +31,7 @@ pub struct Terminal<B: Backend> {
     buffers: [FrameBuffer; 2],
     current_buffer_idx: usize,
     viewport: Rect,
+    cursor_position: Option<Position>,
 }
 
 impl<B: Backend> Terminal<B> {
@@ -51,6 +52,7 @@ impl<B: Backend> Terminal<B> {
             buffers: [FrameBuffer::empty(viewport), FrameBuffer::empty(viewport)],
             current_buffer_idx: 0,
             viewport,
+            cursor_position: None,
         })
     }
 
@@ -62,13 +64,13 @@ impl<B: Backend> Terminal<B> {
         &mut self.buffers[self.current_buffer_idx]
     }
 
+    /// Renders a frame, sending only the cells that changed since the last one. The cursor is
+    /// hidden while cells are drawn so that it does not flicker across the screen, and is left
+    /// alone entirely when nothing changed.
     pub fn draw<F>(&mut self, f: F) -> Result<()>
     where
         F: FnOnce(&mut Frame<B>) -> Result<()>,
     {
-        self.hide_cursor()?;
-        self.position_cursor(&Position::default())?;
-
         let mut frame = Frame {
             terminal: self,
             cursor_position: Position::default(),
@@ -76,13 +78,19 @@ impl<B: Backend> Terminal<B> {
 
         f(&mut frame)?;
 
-        let Position { x, y } = *frame.cursor_position();
-
-        self.flush()?;
+        let position = *frame.cursor_position();
+        let changed = !self.buffers[1 - self.current_buffer_idx]
+            .diff(&self.buffers[self.current_buffer_idx])
+            .is_empty();
 
-        self.position_cursor(&Position { x, y })?;
-
-        self.show_cursor()?;
+        if changed {
+            self.hide_cursor()?;
+            self.flush()?;
+            self.position_cursor(&position)?;
+            self.show_cursor()?;
+        } else if self.cursor_position != Some(position) {
+            self.position_cursor(&position)?;
+        }
 
         self.swap_buffers();
 
@@ -102,6 +110,7 @@ impl<B: Backend> Terminal<B> {
     }
 
     pub fn position_cursor(&mut self, position: &Position) -> Result<()> {
+        self.cursor_position = Some(*position);
         self.backend
             .position_cursor(position.x, position.y)
             .context("unable to position cursor")
@@ -119,6 +128,12 @@ impl<B: Backend> Terminal<B> {
     pub fn viewport(&self) -> Rect {
         self.viewport
     }
+
+    /// The text of the last frame drawn, so that tests can compare the whole screen.
+    #[cfg(test)]
+    pub fn render_to_string(&self) -> String {
+        self.buffers[1 - self.current_buffer_idx].to_string()
+    }
 }
 
 impl<B: Backend> Drop for Terminal<B> {
@@ -132,3 +147,116 @@ impl<B: Backend> Drop for Terminal<B> {
             .expect("unable to disable raw mode");
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        document::{Buffer, Document},
+        editor::Mode,
+        io::mock::{Backend as MockBackend, Call},
+        ops::Command,
+        status_bar::StatusBar,
+        ui::style::Style,
+    };
+
+    struct Text(&'static str);
+
+    impl Component for Text {
+        fn render(&self, buffer: &mut FrameBuffer) {
+            buffer.write_line(0, self.0, &Style::default());
+        }
+    }
+
+    #[test]
+    fn test_draw_sends_changed_cells_to_backend() {
+        let mut terminal = Terminal::new(MockBackend::new(Rect::new(4, 2))).unwrap();
+        assert_eq!(
+            terminal.backend.calls(),
+            &[Call::EnableRawMode, Call::EnterAlternateScreen]
+        );
+        terminal.backend.clear_calls();
+
+        terminal
+            .draw(|frame| {
+                frame.render(&Text("ab"));
+                frame.set_cursor_position(Position::new(2, 0));
+                Ok(())
+            })
+            .unwrap();
+
+        let calls = terminal.backend.calls();
+        let drawn: Vec<&str> = calls
+            .iter()
+            .find_map(|call| match call {
+                Call::Draw(drawn) => Some(drawn.iter().map(|cell| &cell.symbol()[..]).collect()),
+                _ => None,
+            })
+            .unwrap();
+
+        assert_eq!(drawn, vec!["a", "b"]);
+        assert!(calls.contains(&Call::PositionCursor(2, 0)));
+        assert_eq!(calls.last(), Some(&Call::Flush));
+    }
+
+    #[test]
+    fn test_render_to_string_snapshots_edited_buffer() {
+        let mut terminal = Terminal::new(MockBackend::new(Rect::new(40, 5))).unwrap();
+        let mut buffer = Buffer::new(Document::from("hello\nworld"), Rect::new(40, 4));
+        let mut status_bar = StatusBar::new(Rect::positioned(40, 1, 0, 4));
+
+        for command in [
+            Command::MoveCursorDown(1),
+            Command::InsertChar('!'),
+            Command::InsertLineBreak,
+            Command::InsertChar('x'),
+        ] {
+            buffer.proccess_command(command).unwrap();
+        }
+        status_bar.update(Mode::Insert, &buffer);
+
+        terminal
+            .draw(|frame| {
+                frame.render(&buffer);
+                frame.render(&status_bar);
+                Ok(())
+            })
+            .unwrap();
+
+        assert_eq!(
+            terminal.render_to_string(),
+            "hello\n\
+             !\n\
+             xworld\n\
+             ~\n\
+             Mode: [INSERT]    File: [No Name]    [+]"
+        );
+    }
+
+    #[test]
+    fn test_unchanged_frame_leaves_cursor_alone() {
+        let mut terminal = Terminal::new(MockBackend::new(Rect::new(4, 2))).unwrap();
+        let draw = |terminal: &mut Terminal<MockBackend>, x| {
+            terminal.backend.clear_calls();
+            terminal
+                .draw(|frame| {
+                    frame.render(&Text("ab"));
+                    frame.set_cursor_position(Position::new(x, 0));
+                    Ok(())
+                })
+                .unwrap();
+        };
+
+        draw(&mut terminal, 2);
+        assert!(terminal.backend.calls().contains(&Call::HideCursor));
+
+        draw(&mut terminal, 2);
+        assert_eq!(terminal.backend.calls(), &[Call::Flush]);
+
+        draw(&mut terminal, 1);
+        assert_eq!(
+            terminal.backend.calls(),
+            &[Call::PositionCursor(1, 0), Call::Flush]
+        );
+    }
+}
@@ -68,7 +68,7 @@ impl FrameBuffer {
             }
         }
 
-        Self { cells, area }
+        Self { area, cells }
     }
 
     pub fn diff<'a>(&self, other: &'a FrameBuffer) -> Vec<&'a Cell> {
@@ -108,7 +108,7 @@ impl FrameBuffer {
             self.cells[cell_idx] = Cell::new(
                 self.cells[cell_idx].position.x,
                 self.cells[cell_idx].position.y,
-                &grapheme,
+                grapheme,
                 style.clone(),
             );
         }
@@ -117,4 +117,55 @@ impl FrameBuffer {
             self.cells[i].reset();
         }
     }
+
+    /// Writes the string starting at column `x` of the line, leaving the rest of the line
+    /// untouched. Anything past the end of the line is clipped.
+    pub fn write_at(&mut self, x: usize, line_number: usize, string: &str, style: &Style) {
+        let index = self.index_of(&Position::new(0, line_number)).unwrap();
+
+        for (i, grapheme) in string[..]
+            .graphemes(true)
+            .enumerate()
+            .take(self.area.width.saturating_sub(x))
+        {
+            let cell_idx = index + x + i;
+            self.cells[cell_idx] = Cell::new(
+                self.cells[cell_idx].position.x,
+                self.cells[cell_idx].position.y,
+                grapheme,
+                style.clone(),
+            );
+        }
+    }
+}
+
+/// The text on screen, one row per line, with the blank space at the end of each row left
+/// out. Styles are ignored, which makes it suitable for comparing whole screens in tests.
+impl Display for FrameBuffer {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        for (y, row) in self.cells.chunks(self.area.width.max(1)).enumerate() {
+            if y > 0 {
+                writeln!(f)?;
+            }
+
+            let line: String = row.iter().map(|cell| &cell.symbol[..]).collect();
+            write!(f, "{}", line.trim_end_matches(' '))?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display_joins_rows_without_trailing_blanks() {
+        let mut buffer = FrameBuffer::empty(Rect::new(5, 3));
+        buffer.write_line(0, "ab", &Style::default());
+        buffer.write_at(2, 2, "c d", &Style::default());
+
+        assert_eq!(buffer.to_string(), "ab\n\n  c d");
+    }
 }
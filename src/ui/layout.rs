@@ -1,4 +1,5 @@
 use crate::ui::FrameBuffer;
+use std::cmp::Ordering;
 
 pub trait Component {
     fn render(&self, buffer: &mut FrameBuffer);
@@ -22,6 +23,49 @@ impl From<(usize, usize)> for Position {
     }
 }
 
+/// Positions are ordered the way they appear in a document: by row, then by column.
+impl Ord for Position {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.y, self.x).cmp(&(other.y, other.x))
+    }
+}
+
+impl PartialOrd for Position {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// The text between two positions, such as the ground covered by a motion. The end is
+/// exclusive, and may come before the start until the span is normalized.
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+pub struct Span {
+    pub start: Position,
+    pub end: Position,
+}
+
+impl Span {
+    pub fn new(start: Position, end: Position) -> Self {
+        Self { start, end }
+    }
+
+    /// The same span with its endpoints swapped if the end comes before the start.
+    pub fn normalized(self) -> Self {
+        if self.end < self.start {
+            Self::new(self.end, self.start)
+        } else {
+            self
+        }
+    }
+
+    /// Whether the position lies within the span, whichever way round its endpoints are.
+    pub fn contains(&self, position: &Position) -> bool {
+        let Self { start, end } = self.normalized();
+
+        start <= *position && *position < end
+    }
+}
+
 #[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
 pub struct Rect {
     pub width: usize,
@@ -66,9 +110,65 @@ impl Rect {
         self.position.y + self.height
     }
 
+    /// A rect of the given size centered within this one. A size larger than this rect is
+    /// clamped to it, so the result never extends past its edges.
+    pub fn centered(&self, width: usize, height: usize) -> Self {
+        let width = width.min(self.width);
+        let height = height.min(self.height);
+
+        Self::positioned(
+            width,
+            height,
+            self.left() + (self.width - width) / 2,
+            self.top() + (self.height - height) / 2,
+        )
+    }
+
     pub fn contains(&self, position: &Position) -> bool {
         let Position { x, y } = *position;
 
         x >= self.left() && x < self.right() && y >= self.top() && y < self.bottom()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_positions_order_by_row_then_column() {
+        assert!(Position::new(9, 0) < Position::new(0, 1));
+        assert!(Position::new(2, 3) < Position::new(5, 3));
+        assert_eq!(
+            Position::new(4, 2).cmp(&Position::new(4, 2)),
+            Ordering::Equal
+        );
+        assert_eq!(
+            Position::new(0, 2).max(Position::new(7, 1)),
+            Position::new(0, 2)
+        );
+    }
+
+    #[test]
+    fn test_span_normalized() {
+        let span = Span::new(Position::new(0, 4), Position::new(3, 1));
+
+        assert_eq!(
+            span.normalized(),
+            Span::new(Position::new(3, 1), Position::new(0, 4))
+        );
+        assert_eq!(span.normalized().normalized(), span.normalized());
+    }
+
+    #[test]
+    fn test_span_contains() {
+        let span = Span::new(Position::new(2, 3), Position::new(5, 1));
+
+        assert!(span.contains(&Position::new(5, 1)));
+        assert!(span.contains(&Position::new(80, 2)));
+        assert!(span.contains(&Position::new(0, 3)));
+        assert!(!span.contains(&Position::new(2, 3)));
+        assert!(!span.contains(&Position::new(4, 1)));
+        assert!(!span.contains(&Position::new(0, 4)));
+    }
+}
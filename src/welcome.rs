@@ -0,0 +1,65 @@
+use crate::ui::{
+    layout::{Component, Rect},
+    style::Style,
+    FrameBuffer,
+};
+use unicode_segmentation::UnicodeSegmentation;
+
+/// The introduction shown in place of an empty document when the editor is started without a
+/// file. Each line is centered on its own, with the block of lines centered vertically.
+pub struct Screen {
+    viewport: Rect,
+}
+
+impl Screen {
+    pub fn new(viewport: Rect) -> Self {
+        Self { viewport }
+    }
+
+    fn lines() -> Vec<String> {
+        vec![
+            env!("CARGO_PKG_NAME").into(),
+            format!("version {}", env!("CARGO_PKG_VERSION")),
+            String::new(),
+            "type :q<Enter> to quit".into(),
+        ]
+    }
+}
+
+impl Component for Screen {
+    fn render(&self, buffer: &mut FrameBuffer) {
+        let lines = Self::lines();
+        let block = self.viewport.centered(self.viewport.width, lines.len());
+
+        for (line, text) in (block.top()..block.bottom()).zip(&lines) {
+            let area = block.centered(text.graphemes(true).count(), 1);
+            buffer.write_at(area.left(), line, text, &Style::default());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_version_line_is_centered() {
+        let area = Rect::new(40, 10);
+        let mut frame = FrameBuffer::empty(area);
+
+        Screen::new(area).render(&mut frame);
+
+        let version = format!("version {}", env!("CARGO_PKG_VERSION"));
+        let columns: Vec<usize> = FrameBuffer::empty(area)
+            .diff(&frame)
+            .into_iter()
+            .filter(|cell| cell.position().y == 4)
+            .map(|cell| cell.position().x)
+            .collect();
+
+        let left = columns[0];
+        let right = 40 - (columns[columns.len() - 1] + 1);
+        assert_eq!(columns[columns.len() - 1] + 1 - left, version.len());
+        assert!(right == left || right == left + 1);
+    }
+}
@@ -0,0 +1,319 @@
+use crate::{
+    document::{Buffer, Document, Options},
+    ops::Command,
+    ui::{
+        layout::{Component, Rect},
+        style::{Color, Style},
+        FrameBuffer,
+    },
+};
+
+/// Lays out each open buffer as a horizontal split of the editor area. Every window but the
+/// last is followed by a separator line showing the name of its document.
+pub struct WindowManager {
+    area: Rect,
+    buffers: Vec<Buffer>,
+    heights: Vec<usize>,
+    focused: usize,
+    messages: Vec<String>,
+}
+
+impl WindowManager {
+    pub fn new(area: Rect, document: Document) -> Self {
+        let mut window_manager = Self {
+            area,
+            buffers: vec![Buffer::new(document, area)],
+            heights: vec![area.height],
+            focused: 0,
+            messages: Vec::new(),
+        };
+
+        window_manager.layout();
+        window_manager
+    }
+
+    pub fn focused(&self) -> &Buffer {
+        &self.buffers[self.focused]
+    }
+
+    pub fn focused_mut(&mut self) -> &mut Buffer {
+        &mut self.buffers[self.focused]
+    }
+
+    pub fn buffers(&self) -> impl Iterator<Item = &Buffer> {
+        self.buffers.iter()
+    }
+
+    /// Takes the messages for the user reported by the commands processed since the last call.
+    pub fn drain_messages(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.messages)
+    }
+
+    pub fn len(&self) -> usize {
+        self.buffers.len()
+    }
+
+    /// Splits the focused window in two, opening the document in the new window above it.
+    /// The split is ignored when the focused window is too small to be divided.
+    pub fn split(&mut self, document: Document, options: Options) {
+        let available = self.heights[self.focused].saturating_sub(1);
+
+        if available < 2 {
+            return;
+        }
+
+        let height = available / 2;
+        self.heights[self.focused] = available - height;
+        self.heights.insert(self.focused, height);
+        self.buffers.insert(
+            self.focused,
+            Buffer::with_options(document, Rect::default(), options),
+        );
+
+        self.layout();
+    }
+
+    pub fn process_command(&mut self, command: &Command) {
+        match *command {
+            Command::FocusNextWindow => self.focused = (self.focused + 1) % self.len(),
+            Command::FocusWindowBelow => self.focused = (self.focused + 1).min(self.len() - 1),
+            Command::FocusWindowAbove => self.focused = self.focused.saturating_sub(1),
+            Command::CloseWindow => self.close(self.focused),
+            Command::CloseOtherWindows => self.close_others(),
+            Command::IncreaseWindowHeight(n) => self.resize(n, true),
+            Command::DecreaseWindowHeight(n) => self.resize(n, false),
+            _ => {}
+        }
+    }
+
+    /// Closes the window, giving its space to the window above it (or below when it is the
+    /// first). The last remaining window can not be closed, and neither can a window whose
+    /// document has unsaved changes.
+    fn close(&mut self, index: usize) {
+        if self.len() == 1 {
+            return;
+        }
+
+        if self.buffers[index].is_dirty() {
+            self.messages.push("E37: No write since last change".into());
+            return;
+        }
+
+        // Nothing is left to write the swap file once the window is gone. It only holds
+        // changes that are already saved, so failing to remove it loses nothing.
+        let _ = self.buffers[index].remove_swap();
+
+        let neighbour = index.saturating_sub(1).max(usize::from(index == 0));
+        self.heights[neighbour] += self.heights[index] + 1;
+
+        self.buffers.remove(index);
+        self.heights.remove(index);
+
+        if self.focused >= index && self.focused > 0 {
+            self.focused -= 1;
+        }
+
+        self.layout();
+    }
+
+    /// Closes every window but the focused one, keeping those with unsaved changes.
+    fn close_others(&mut self) {
+        for index in (0..self.len()).rev() {
+            if index != self.focused && !self.buffers[index].is_dirty() {
+                self.close(index);
+            }
+        }
+
+        if self.len() > 1 {
+            self.messages
+                .push("E445: Other window contains changes".into());
+        }
+    }
+
+    /// Grows or shrinks the focused window by trading lines with its neighbour below it (or
+    /// above when it is the last window). Every window keeps at least one line.
+    fn resize(&mut self, n: usize, grow: bool) {
+        if self.len() == 1 {
+            return;
+        }
+
+        let neighbour = if self.focused + 1 < self.len() {
+            self.focused + 1
+        } else {
+            self.focused - 1
+        };
+
+        let (from, to) = if grow {
+            (neighbour, self.focused)
+        } else {
+            (self.focused, neighbour)
+        };
+
+        let n = n.min(self.heights[from].saturating_sub(1));
+        self.heights[from] -= n;
+        self.heights[to] += n;
+
+        self.layout();
+    }
+
+    fn layout(&mut self) {
+        let mut y = self.area.top();
+
+        for (buffer, &height) in self.buffers.iter_mut().zip(&self.heights) {
+            buffer.set_viewport(Rect::positioned(
+                self.area.width,
+                height,
+                self.area.left(),
+                y,
+            ));
+
+            y += height + 1;
+        }
+    }
+}
+
+impl Component for WindowManager {
+    fn render(&self, buffer: &mut FrameBuffer) {
+        let separator_style = Style::new(Color::Rgb(63, 63, 63), Color::Rgb(239, 239, 239));
+        let mut y = self.area.top();
+
+        for (window, &height) in self.buffers.iter().zip(&self.heights) {
+            window.render(buffer);
+            y += height;
+
+            if y < self.area.bottom() {
+                buffer.write_line(y, &window.document_name(), &separator_style);
+                y += 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ui::layout::Position;
+    use std::env;
+
+    fn two_split_layout() -> WindowManager {
+        let mut window_manager = WindowManager::new(Rect::new(80, 21), Document::from("bottom"));
+        window_manager.split(Document::from("top"), Options::default());
+        window_manager
+    }
+
+    #[test]
+    fn test_split_divides_focused_window() {
+        let window_manager = two_split_layout();
+
+        assert_eq!(window_manager.len(), 2);
+        assert_eq!(window_manager.heights, vec![10, 10]);
+        assert_eq!(
+            window_manager.focused().cursor_position(),
+            Position::new(0, 0)
+        );
+        assert_eq!(
+            window_manager.buffers[1].cursor_position(),
+            Position::new(0, 11)
+        );
+    }
+
+    #[test]
+    fn test_focus_cycling() {
+        let mut window_manager = two_split_layout();
+
+        window_manager.process_command(&Command::FocusNextWindow);
+        assert_eq!(window_manager.focused, 1);
+
+        window_manager.process_command(&Command::FocusNextWindow);
+        assert_eq!(window_manager.focused, 0);
+
+        window_manager.process_command(&Command::FocusWindowAbove);
+        assert_eq!(window_manager.focused, 0);
+
+        window_manager.process_command(&Command::FocusWindowBelow);
+        window_manager.process_command(&Command::FocusWindowBelow);
+        assert_eq!(window_manager.focused, 1);
+    }
+
+    #[test]
+    fn test_close_window() {
+        let mut window_manager = two_split_layout();
+
+        window_manager.process_command(&Command::CloseWindow);
+        assert_eq!(window_manager.len(), 1);
+        assert_eq!(window_manager.heights, vec![21]);
+        assert_eq!(window_manager.focused().lines_in_document(), 1);
+        assert_eq!(
+            window_manager.focused().cursor_position(),
+            Position::new(0, 0)
+        );
+
+        window_manager.process_command(&Command::CloseWindow);
+        assert_eq!(window_manager.len(), 1);
+    }
+
+    #[test]
+    fn test_close_window_refuses_unsaved_changes() {
+        let mut window_manager = two_split_layout();
+        window_manager
+            .focused_mut()
+            .proccess_command(Command::InsertChar('!'))
+            .unwrap();
+
+        window_manager.process_command(&Command::CloseWindow);
+        assert_eq!(window_manager.len(), 2);
+        assert_eq!(
+            window_manager.drain_messages(),
+            vec!["E37: No write since last change"]
+        );
+
+        window_manager.process_command(&Command::FocusNextWindow);
+        window_manager.process_command(&Command::CloseOtherWindows);
+        assert_eq!(window_manager.len(), 2);
+        assert_eq!(
+            window_manager.drain_messages(),
+            vec!["E445: Other window contains changes"]
+        );
+    }
+
+    #[test]
+    fn test_close_window_removes_swap_file() {
+        let path = env::temp_dir().join("vie_test_close_window.txt");
+        let document = Document::open(path.to_str().unwrap()).unwrap();
+        let swap = document.swap_file_name().unwrap();
+        document.write_swap().unwrap();
+
+        let mut window_manager = two_split_layout();
+        window_manager.split(document, Options::default());
+        window_manager.process_command(&Command::CloseWindow);
+
+        assert_eq!(window_manager.len(), 2);
+        assert!(window_manager.drain_messages().is_empty());
+        assert!(!swap.exists());
+    }
+
+    #[test]
+    fn test_close_other_windows() {
+        let mut window_manager = WindowManager::new(Rect::new(80, 21), Document::from("bottom"));
+        window_manager.split(Document::from("middle\nmiddle"), Options::default());
+        window_manager.split(Document::default(), Options::default());
+        window_manager.process_command(&Command::FocusWindowBelow);
+
+        window_manager.process_command(&Command::CloseOtherWindows);
+
+        assert_eq!(window_manager.len(), 1);
+        assert_eq!(window_manager.heights, vec![21]);
+        assert_eq!(window_manager.focused().lines_in_document(), 2);
+    }
+
+    #[test]
+    fn test_resize() {
+        let mut window_manager = two_split_layout();
+
+        window_manager.process_command(&Command::IncreaseWindowHeight(3));
+        assert_eq!(window_manager.heights, vec![13, 7]);
+
+        window_manager.process_command(&Command::DecreaseWindowHeight(20));
+        assert_eq!(window_manager.heights, vec![1, 19]);
+    }
+}